@@ -3,8 +3,12 @@ use mods::extract_archive::extract_mod_archive;
 use mods::patch_modinfo::patch_modinfo_xml_command;
 use mods::{
     backup::{backup_mod_to_temp, cleanup_mod_backup, restore_mod_from_temp},
+    compatibility::check_mods_compatibility,
+    dependencies::resolve_mod_dependencies,
     profiles::{create_empty_profile, delete_profile, list_profiles},
-    traversal::scan_civ_mods,
+    translations::get_translation_coverage,
+    traversal::{detect_mod_conflicts, scan_civ_mods},
+    update_diff::diff_mod_update,
 };
 use std::fs;
 use tauri::Manager;
@@ -128,6 +132,11 @@ pub fn run() {
             get_mods_folder,
             extract_mod_archive,
             scan_civ_mods,
+            detect_mod_conflicts,
+            resolve_mod_dependencies,
+            get_translation_coverage,
+            check_mods_compatibility,
+            diff_mod_update,
             patch_modinfo_xml_command,
             // Security
             redact_path,
@@ -0,0 +1,136 @@
+use modinfo_parser::ModVersion;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::mods::get_civ_game_folder::{detect_game_version, get_civ7_game_folder};
+use crate::mods::traversal::read_installed_mods;
+
+#[derive(Serialize)]
+pub struct ModCompatibility {
+    folder_name: String,
+    modinfo_id: String,
+    /// The `<Compatibility>` property, with its `LOC_` key resolved.
+    compatibility: Option<String>,
+    /// The parsed game version range, e.g. `>=1.1, <1.2`.
+    range: Option<String>,
+    /// `None` when the mod doesn't declare a game version range.
+    compatible: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct GameCompatibility {
+    /// The version of the installed game, e.g. `1.2.1.0`.
+    game_version: String,
+    mods: Vec<ModCompatibility>,
+}
+
+/// Checks every installed mod against the version of the installed game, so
+/// the app can flag mods that are not declared compatible with it after a
+/// game update.
+///
+/// Without a game folder, the game is looked for in the default Steam library.
+#[tauri::command]
+pub fn check_mods_compatibility(
+    mods_folder_path: Option<String>,
+    game_folder_path: Option<String>,
+) -> Result<GameCompatibility, String> {
+    let game_folder = game_folder_path
+        .map(PathBuf::from)
+        .or_else(get_civ7_game_folder)
+        .ok_or("Game install folder not found")?;
+    let game_version = detect_game_version(&game_folder).ok_or_else(|| {
+        format!(
+            "Could not detect the game version in {}",
+            game_folder.display()
+        )
+    })?;
+    Ok(GameCompatibility {
+        mods: check_compatibility(mods_folder_path, &game_version)?,
+        game_version,
+    })
+}
+
+fn check_compatibility(
+    mods_folder_path: Option<String>,
+    game_version: &str,
+) -> Result<Vec<ModCompatibility>, String> {
+    let game_version = ModVersion::parse(game_version);
+    let installed = read_installed_mods(mods_folder_path)?;
+    Ok(installed
+        .into_iter()
+        .map(|installed| {
+            let strings = Path::new(&installed.modinfo_path)
+                .parent()
+                .map(|mod_folder| installed.modinfo.load_text(mod_folder))
+                .unwrap_or_default();
+            let range = installed.modinfo.game_compatibility(&strings);
+            ModCompatibility {
+                compatibility: installed
+                    .modinfo
+                    .compatibility_text(&strings)
+                    .map(str::to_string),
+                compatible: installed
+                    .modinfo
+                    .is_compatible_with(&strings, &game_version),
+                range: range.map(|range| range.to_string()),
+                modinfo_id: installed.modinfo.id,
+                folder_name: installed.folder_name,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_mods_compatibility() {
+        let dir = tempdir().unwrap();
+        for (id, compatibility) in [
+            ("a_mod", "<Compatibility>1.1.x</Compatibility>"),
+            (
+                "b_mod",
+                "<Compatibility>LOC_B_MOD_COMPATIBILITY</Compatibility>",
+            ),
+            ("c_mod", ""),
+        ] {
+            let folder = dir.path().join(id);
+            fs::create_dir(&folder).unwrap();
+            fs::write(
+                folder.join(format!("{id}.modinfo")),
+                format!(
+                    r#"<Mod id="{id}"><Properties>{compatibility}</Properties><LocalizedText><File>text.xml</File></LocalizedText></Mod>"#
+                ),
+            )
+            .unwrap();
+            fs::write(
+                folder.join("text.xml"),
+                r#"<Database><EnglishText><Row Tag="LOC_B_MOD_COMPATIBILITY"><Text>1.0.2 or later</Text></Row></EnglishText></Database>"#,
+            )
+            .unwrap();
+        }
+
+        let compatibility =
+            check_compatibility(Some(dir.path().to_string_lossy().to_string()), "1.2.0").unwrap();
+        let compatible: Vec<_> = compatibility
+            .iter()
+            .map(|c| (c.modinfo_id.as_str(), c.compatible))
+            .collect();
+        assert_eq!(
+            compatible,
+            [
+                ("a_mod", Some(false)),
+                ("b_mod", Some(true)),
+                ("c_mod", None)
+            ]
+        );
+        assert_eq!(
+            compatibility[1].compatibility.as_deref(),
+            Some("1.0.2 or later")
+        );
+        assert_eq!(compatibility[1].range.as_deref(), Some(">=1.0.2"));
+    }
+}
@@ -0,0 +1,117 @@
+use modinfo_parser::{DependencyGraph, DependencyReport, ModIdentity};
+use serde::Serialize;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::mods::traversal::read_installed_mods;
+
+#[derive(Serialize)]
+pub struct ModDependencies {
+    #[serde(flatten)]
+    report: DependencyReport,
+    dot: String,
+    mermaid: String,
+}
+
+/// Ids of the modules shipped with the game, in `Base/modules` and `DLC` of
+/// its install folder, which mods can depend on without them being installed.
+fn shipped_module_ids(game_folder: &Path) -> Vec<String> {
+    ["Base/modules", "DLC"]
+        .iter()
+        .flat_map(|folder| WalkDir::new(game_folder.join(folder)).max_depth(3))
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && entry.path().extension().is_some_and(|ext| ext == "modinfo")
+        })
+        .filter_map(|entry| match ModIdentity::open(entry.path()) {
+            Ok(identity) => identity.id,
+            Err(err) => {
+                log::warn!("Failed to read module {}: {err}", entry.path().display());
+                None
+            }
+        })
+        .collect()
+}
+
+/// Resolves the dependencies between the installed mods, so the app can warn
+/// about missing or circular dependencies before the game is launched.
+///
+/// Without the game folder, only the modules of the base game are known: mods
+/// requiring a DLC are reported with a missing dependency.
+#[tauri::command]
+pub fn resolve_mod_dependencies(
+    mods_folder_path: Option<String>,
+    game_folder_path: Option<String>,
+) -> Result<ModDependencies, String> {
+    let installed = read_installed_mods(mods_folder_path)?;
+    let shipped = game_folder_path
+        .map(|path| shipped_module_ids(Path::new(&path)))
+        .unwrap_or_default();
+    let graph = DependencyGraph::new(installed.iter().map(|installed| &installed.modinfo))
+        .with_builtin(shipped);
+    let report = graph.report();
+    if !report.missing.is_empty() {
+        log::warn!("Missing mod dependencies: {:?}", report.missing);
+    }
+    if !report.cycles.is_empty() {
+        log::warn!("Circular mod dependencies: {:?}", report.cycles);
+    }
+    Ok(ModDependencies {
+        report,
+        dot: graph.to_dot(),
+        mermaid: graph.to_mermaid(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_resolve_mod_dependencies() {
+        let dir = tempdir().unwrap();
+        let game = tempdir().unwrap();
+        let dlc = game.path().join("DLC").join("some-dlc");
+        fs::create_dir_all(&dlc).unwrap();
+        fs::write(dlc.join("some-dlc.modinfo"), r#"<Mod id="some-dlc"/>"#).unwrap();
+
+        for (id, dependency) in [
+            ("a_mod", "b_mod"),
+            ("b_mod", "base-standard"),
+            ("c_mod", "gone"),
+            ("d_mod", "some-dlc"),
+        ] {
+            let folder = dir.path().join(id);
+            fs::create_dir(&folder).unwrap();
+            fs::write(
+                folder.join(format!("{id}.modinfo")),
+                format!(
+                    r#"<Mod id="{id}"><Dependencies><Mod id="{dependency}"/></Dependencies></Mod>"#
+                ),
+            )
+            .unwrap();
+        }
+
+        let mods_folder_path = || Some(dir.path().to_string_lossy().to_string());
+        let dependencies = resolve_mod_dependencies(
+            mods_folder_path(),
+            Some(game.path().to_string_lossy().to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            dependencies.report.order,
+            ["b_mod", "a_mod", "c_mod", "d_mod"]
+        );
+        assert_eq!(dependencies.report.missing.len(), 1);
+        assert_eq!(dependencies.report.missing[0].dependency_id, "gone");
+        assert!(dependencies.report.cycles.is_empty());
+        assert!(dependencies.dot.contains("\"a_mod\" -> \"b_mod\";"));
+        assert!(dependencies.dot.contains("\"some-dlc\" [shape=box];"));
+
+        let dependencies = resolve_mod_dependencies(mods_folder_path(), None).unwrap();
+        assert_eq!(dependencies.report.missing.len(), 2);
+    }
+}
@@ -0,0 +1,125 @@
+use dirs::home_dir;
+use std::env::consts::OS;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Steam folder of the game, when installed in the default Steam library.
+pub fn get_civ7_game_folder() -> Option<PathBuf> {
+    let steam = match OS {
+        "windows" => PathBuf::from(r"C:\Program Files (x86)\Steam"),
+        "macos" => home_dir()?
+            .join("Library")
+            .join("Application Support")
+            .join("Steam"),
+        "linux" => home_dir()?.join(".local").join("share").join("Steam"),
+        _ => return None,
+    };
+    let game_path = steam
+        .join("steamapps")
+        .join("common")
+        .join("Sid Meier's Civilization VII");
+
+    if game_path.exists() {
+        Some(game_path)
+    } else {
+        None
+    }
+}
+
+/// Reads the game version, e.g. `1.2.1.0`, from the version resource of the
+/// game's Windows executable in `Base/Binaries`, which Proton installs have
+/// too. `None` for other builds.
+pub fn detect_game_version(game_folder: &Path) -> Option<String> {
+    WalkDir::new(game_folder.join("Base").join("Binaries"))
+        .max_depth(2)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && entry
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+        })
+        .find_map(|entry| read_product_version(entry.path()))
+}
+
+/// Finds the `ProductVersion` string of a `VS_VERSIONINFO` resource, stored
+/// as UTF-16 with its key, without loading the whole executable at once.
+fn read_product_version(path: &Path) -> Option<String> {
+    // The value follows the key and its padding within this many bytes
+    const VALUE_LENGTH: usize = 128;
+    let key: Vec<u8> = "ProductVersion\0"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+
+    let mut file = File::open(path).ok()?;
+    let mut buffer: Vec<u8> = vec![];
+    let mut chunk = vec![0; 1 << 20];
+    loop {
+        let read = file.read(&mut chunk).ok()?;
+        buffer.extend_from_slice(&chunk[..read]);
+        let start = buffer
+            .windows(key.len())
+            .position(|window| window == key.as_slice());
+        match start {
+            Some(start) => {
+                let value_start = start + key.len();
+                if read == 0 || buffer.len() >= value_start + VALUE_LENGTH {
+                    let value = &buffer[value_start..buffer.len().min(value_start + VALUE_LENGTH)];
+                    return read_utf16_value(value);
+                }
+            }
+            None if read == 0 => return None,
+            // Keep enough to find a key split across chunks
+            None => {
+                buffer.drain(..buffer.len().saturating_sub(key.len()));
+            }
+        }
+    }
+}
+
+/// Reads a NUL-terminated UTF-16 string, after its leading padding.
+fn read_utf16_value(bytes: &[u8]) -> Option<String> {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .skip_while(|unit| *unit == 0)
+        .take_while(|unit| *unit != 0)
+        .collect();
+    let value = String::from_utf16(&units).ok()?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_detect_game_version() {
+        let dir = tempdir().unwrap();
+        assert_eq!(detect_game_version(dir.path()), None);
+
+        let binaries = dir.path().join("Base").join("Binaries").join("Win64");
+        fs::create_dir_all(&binaries).unwrap();
+        let mut executable = b"MZ\x90\0".repeat(1000);
+        executable.extend(utf16("FileVersion\0\0"));
+        executable.extend(utf16("1.2.1.0\0"));
+        executable.extend(utf16("ProductVersion\0\0"));
+        executable.extend(utf16("1.2.1.0 \0"));
+        executable.extend(b"\0\0\x01\x02");
+        fs::write(binaries.join("CivilizationVII.exe"), executable).unwrap();
+
+        assert_eq!(detect_game_version(dir.path()).as_deref(), Some("1.2.1.0"));
+    }
+}
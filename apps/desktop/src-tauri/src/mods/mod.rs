@@ -1,6 +1,11 @@
 pub mod backup;
+pub mod compatibility;
+pub mod dependencies;
 pub mod extract_archive;
+pub mod get_civ_game_folder;
 pub mod get_civ_mods_folder;
 pub mod patch_modinfo;
 pub mod profiles;
+pub mod translations;
 pub mod traversal;
+pub mod update_diff;
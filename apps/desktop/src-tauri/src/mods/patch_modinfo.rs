@@ -1,14 +1,10 @@
 use diffy::create_patch;
 use diffy::Patch;
-use quick_xml::errors::IllFormedError;
-use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
-use quick_xml::Error;
-use quick_xml::Reader;
-use quick_xml::Writer;
+use modinfo_parser::encoding;
+use modinfo_parser::{DetectedEncoding, Document};
 use serde::Deserialize;
 use std::collections::BTreeMap;
 use std::fs;
-use std::io::Cursor;
 use std::path::Path;
 use tauri::AppHandle;
 
@@ -55,82 +51,54 @@ fn map_props_to_xml(props: &CivModsProperties) -> BTreeMap<String, String> {
     props_map
 }
 
+/// Reads a modinfo file in whatever encoding it was saved with.
+fn read_modinfo(path: &Path) -> Result<(String, DetectedEncoding), String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read modinfo file: {e}"))?;
+    let (text, detected, repairs) =
+        encoding::decode(&bytes).map_err(|e| format!("Failed to decode modinfo file: {e}"))?;
+    if !repairs.is_empty() {
+        log::warn!(
+            "patch/hash: Replaced {} invalid byte sequences in {:?}",
+            repairs.len(),
+            path
+        );
+    }
+    Ok((text, detected))
+}
+
 /// Applies a patch to the <Properties> section of a modinfo XML file.
 /// Adds CivMods custom metadata and generates a ._modinfo_civmods_patch diff file.
 pub fn patch_modinfo_xml<P: AsRef<Path>>(
     modinfo_path: P,
     properties: CivModsProperties,
 ) -> Result<(), String> {
-    let original = fs::read_to_string(&modinfo_path)
-        .map_err(|e| format!("Failed to read modinfo file: {e}"))?;
-
-    let mut reader = Reader::from_str(&original);
-    let mut writer = Writer::new(Cursor::new(Vec::new()));
-    let mut buf = Vec::new();
-
-    let props_map = map_props_to_xml(&properties);
-
-    // Helpers for navigating XML
-    let mut in_properties = false;
-    let mut inserted = false;
-    let mut indent_prefix: Option<String> = None;
-
-    loop {
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) if e.name().as_ref().eq_ignore_ascii_case(b"Properties") => {
-                in_properties = true;
-                writer.write_event(Event::Start(e.to_owned())).unwrap();
-            }
-            Ok(Event::Text(e)) if in_properties => {
-                let text = e.unescape().unwrap_or_default();
-                if text.trim().is_empty() {
-                    // Get only the indentation (last line's spaces/tabs)
-                    let indent_only = text.lines().last().unwrap_or_default();
-                    indent_prefix = Some(indent_only.to_string());
-                }
-                writer.write_event(Event::Text(e)).unwrap();
-            }
-            Ok(Event::End(ref e))
-                if in_properties && e.name().as_ref().eq_ignore_ascii_case(b"Properties") =>
-            {
-                if !inserted {
-                    log::info!("patch/hash: Inserting custom properties into modinfo file, indent is '{:?}'", indent_prefix);
-                    insert_custom_properties(
-                        &mut writer,
-                        &props_map,
-                        indent_prefix.as_deref().unwrap_or("    "),
-                    )?;
-
-                    inserted = true;
-                }
-                writer.write_event(Event::End(e.to_owned())).unwrap();
-                in_properties = false;
-            }
-            Ok(Event::Eof) => break,
-            Ok(e) => {
-                writer.write_event(e).unwrap();
-            }
-            Err(Error::IllFormed(IllFormedError::MismatchedEndTag { expected, found })) => {
-                log::warn!("Mismatched end tag: expected: {expected:?}, found: {found:?}");
-                writer
-                    .write_event(Event::End(BytesEnd::new(expected)))
-                    .unwrap();
-            }
-            Err(Error::IllFormed(IllFormedError::MissingEndTag(tag))) => {
-                log::warn!("Missing end tag: {tag:?}");
-                writer.write_event(Event::End(BytesEnd::new(tag))).unwrap();
-            }
-            Err(Error::IllFormed(IllFormedError::UnmatchedEndTag(tag))) => {
-                log::warn!("Unmatched end tag: {tag:?}");
-                writer.write_event(Event::End(BytesEnd::new(tag))).unwrap();
-            }
-            Err(e) => return Err(format!("Error parsing XML: {e}")),
-        }
-        buf.clear();
+    let (original, detected) = read_modinfo(modinfo_path.as_ref())?;
+
+    let mut document =
+        Document::parse(original.as_str()).map_err(|e| format!("Error parsing XML: {e}"))?;
+    let already_patched = document.has_property("CivModsInternalVersionId");
+
+    log::info!("patch/hash: Inserting custom properties into modinfo file");
+    if !already_patched {
+        document
+            .add_property_comment(" Added automatically by CivMods: Do not edit ")
+            .map_err(|e| format!("Error patching modinfo: {e}"))?;
+    }
+    for (key, value) in map_props_to_xml(&properties) {
+        document
+            .set_property(&key, &value)
+            .map_err(|e| format!("Error patching modinfo: {e}"))?;
     }
 
-    let modified_bytes = writer.into_inner().into_inner();
-    let modified = String::from_utf8(modified_bytes).map_err(|e| format!("UTF-8 error: {e}"))?;
+    // The scan reads the injected properties back through the same parser
+    match document.to_mod() {
+        Ok(patched) if patched.properties.civmods_internal_version_id.is_none() => {
+            log::warn!("patch/hash: Patched modinfo file is missing CivMods properties");
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("patch/hash: Patched modinfo file cannot be parsed: {e}"),
+    }
+    let modified = document.into_string();
 
     // Generate and save diff
     let patch = create_patch(&modified, &original);
@@ -139,42 +107,9 @@ pub fn patch_modinfo_xml<P: AsRef<Path>>(
     fs::write(patch_path, patch.to_string())
         .map_err(|e| format!("Failed to write patch file: {e}"))?;
 
-    // Apply patch
-    fs::write(&modinfo_path, modified).map_err(|e| format!("Failed to write modinfo file: {e}"))?;
-
-    Ok(())
-}
-
-/// Writes a set of tag-value pairs as XML elements under <Properties>
-fn insert_custom_properties<W: std::io::Write>(
-    writer: &mut Writer<W>,
-    props: &BTreeMap<String, String>,
-    indent: &str,
-) -> Result<(), String> {
-    writer
-        .write_event(Event::Comment(BytesText::new(
-            " Added automatically by CivMods: Do not edit ",
-        )))
-        .unwrap();
-    // Write comment before inserted properties
-    writer
-        .write_event(Event::Text(BytesText::new(&format!("\n{}", indent))))
-        .unwrap();
-
-    for (key, value) in props {
-        let element = BytesStart::new(key.as_str());
-        writer.write_event(Event::Start(element)).unwrap();
-        writer
-            .write_event(Event::Text(BytesText::new(value)))
-            .unwrap();
-        writer
-            .write_event(Event::End(BytesEnd::new(key.as_str())))
-            .unwrap();
-
-        writer
-            .write_event(Event::Text(BytesText::new(&format!("\n{}", indent))))
-            .unwrap();
-    }
+    // Apply patch, keeping the encoding of the original file
+    fs::write(&modinfo_path, encoding::encode(&modified, detected))
+        .map_err(|e| format!("Failed to write modinfo file: {e}"))?;
 
     Ok(())
 }
@@ -187,8 +122,7 @@ pub fn restore_patched_modinfo_xml(modinfo_path: &Path) -> Result<Option<Vec<u8>
         return Ok(None); // No patch found
     }
 
-    let original = fs::read_to_string(modinfo_path)
-        .map_err(|e| format!("Failed to read modinfo file: {e}"))?;
+    let (original, detected) = read_modinfo(modinfo_path)?;
     let patch_str =
         fs::read_to_string(patch_path).map_err(|e| format!("Failed to read patch file: {e}"))?;
 
@@ -198,7 +132,7 @@ pub fn restore_patched_modinfo_xml(modinfo_path: &Path) -> Result<Option<Vec<u8>
     let restored =
         diffy::apply(&original, &patch).map_err(|e| format!("Failed to apply patch: {e}"))?;
 
-    Ok(Some(restored.into_bytes()))
+    Ok(Some(encoding::encode(&restored, detected)))
 }
 
 #[cfg(test)]
@@ -244,4 +178,72 @@ mod tests {
 
         assert_eq!(restored, Some(original.as_bytes().to_vec()));
     }
+
+    fn properties(internal_version_id: &str) -> CivModsProperties {
+        CivModsProperties {
+            target_modinfo_id: None,
+            target_modinfo_path: None,
+            internal_version_id: internal_version_id.to_string(),
+            mod_url: "https://test.com".to_string(),
+            mod_version: None,
+            mod_category: None,
+            mod_version_date: None,
+        }
+    }
+
+    #[test]
+    fn test_patch_modinfo_xml_keeps_encoding() {
+        let dir = tempdir().unwrap();
+        let modinfo_path = dir.path().join("TestMod.modinfo");
+
+        // Windows-1252, as saved by older Windows editors
+        let original = b"<Mod><Properties><Name>Caf\xE9</Name></Properties></Mod>";
+        fs::write(&modinfo_path, original).unwrap();
+        patch_modinfo_xml(&modinfo_path, properties("abc")).expect("Failed to patch");
+        let patched = fs::read(&modinfo_path).unwrap();
+        assert!(patched.windows(8).any(|window| window == b"Caf\xE9</Na"));
+        assert_eq!(
+            restore_patched_modinfo_xml(&modinfo_path).unwrap(),
+            Some(original.to_vec())
+        );
+
+        // UTF-16 with a byte order mark
+        let original: Vec<u8> = b"\xFF\xFE"
+            .iter()
+            .copied()
+            .chain(
+                "<Mod><Properties><Name>Café</Name></Properties></Mod>"
+                    .encode_utf16()
+                    .flat_map(u16::to_le_bytes),
+            )
+            .collect();
+        fs::write(&modinfo_path, &original).unwrap();
+        patch_modinfo_xml(&modinfo_path, properties("abc")).expect("Failed to patch");
+        let (patched, detected) = read_modinfo(&modinfo_path).unwrap();
+        assert_eq!(detected.name(), "UTF-16LE");
+        assert!(patched.contains("<CivModsURL>https://test.com</CivModsURL>"));
+        assert_eq!(
+            restore_patched_modinfo_xml(&modinfo_path).unwrap(),
+            Some(original)
+        );
+    }
+
+    #[test]
+    fn test_patch_modinfo_xml_twice() {
+        let dir = tempdir().unwrap();
+        let modinfo_path = dir.path().join("TestMod.modinfo");
+
+        // Not a valid mod, but still patched once
+        fs::write(
+            &modinfo_path,
+            "<Mod><Properties><Name>A</Name></Properties></Mod>",
+        )
+        .unwrap();
+        patch_modinfo_xml(&modinfo_path, properties("abc")).expect("Failed to patch");
+        patch_modinfo_xml(&modinfo_path, properties("def")).expect("Failed to patch");
+        let patched = fs::read_to_string(&modinfo_path).unwrap();
+        assert_eq!(patched.matches("Added automatically by CivMods").count(), 1);
+        assert_eq!(patched.matches("<CivModsInternalVersionId>").count(), 1);
+        assert!(patched.contains("<CivModsInternalVersionId>def</CivModsInternalVersionId>"));
+    }
 }
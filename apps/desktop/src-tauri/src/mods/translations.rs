@@ -0,0 +1,43 @@
+use modinfo_parser::text::LocaleCoverage;
+use modinfo_parser::Mod;
+use std::path::Path;
+
+/// Per-locale translation coverage of an installed mod, compared to en_US.
+#[tauri::command]
+pub fn get_translation_coverage(modinfo_path: String) -> Result<Vec<LocaleCoverage>, String> {
+    let modinfo =
+        Mod::open(&modinfo_path).map_err(|e| format!("Failed to read {modinfo_path}: {e}"))?;
+    let mod_folder = Path::new(&modinfo_path)
+        .parent()
+        .ok_or("Invalid modinfo path")?;
+    Ok(modinfo.translation_coverage(mod_folder))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_get_translation_coverage() {
+        let dir = tempdir().unwrap();
+        let modinfo_path = dir.path().join("a_mod.modinfo");
+        fs::write(
+            &modinfo_path,
+            r#"<Mod id="a_mod"><LocalizedText><File>en.xml</File></LocalizedText><ActionGroups><ActionGroup id="shell" scope="shell"><Actions><UpdateText><Item locale="fr_FR">fr.xml</Item></UpdateText></Actions></ActionGroup></ActionGroups></Mod>"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("en.xml"),
+            r#"<Database><EnglishText><Row Tag="LOC_A"><Text>A</Text></Row></EnglishText></Database>"#,
+        )
+        .unwrap();
+
+        let coverage =
+            get_translation_coverage(modinfo_path.to_string_lossy().to_string()).unwrap();
+        assert_eq!(coverage.len(), 1);
+        assert_eq!(coverage[0].locale, "fr_FR");
+        assert_eq!(coverage[0].missing, ["LOC_A"]);
+    }
+}
@@ -1,11 +1,13 @@
-use quick_xml::de::Text;
-use quick_xml::escape;
-use quick_xml::events::BytesText;
-use serde::{Deserialize, Serialize};
+use modinfo_parser::files::is_hidden_file_name;
+use modinfo_parser::text::DEFAULT_LOCALE;
+use modinfo_parser::{
+    detect_database_conflicts, detect_file_conflicts, detect_text_conflicts, DatabaseConflict,
+    FileConflict, Mod, ModIdentity, ModinfoError, ParsedMod, Repair, TextConflict,
+};
+use serde::Serialize;
 use sha2::{Digest, Sha256};
-use std::borrow::Cow;
 use std::fs::{self, File};
-use std::io::{BufReader, Read, Write};
+use std::io::Read;
 use std::path::Path;
 use walkdir::WalkDir;
 
@@ -19,31 +21,14 @@ pub struct ModInfo {
     folder_hash: String,
     folder_name: String,
     civmods_internal_version_id: Option<String>,
+    modinfo_repairs: Vec<Repair>, // Fixes applied to a malformed modinfo XML
+    modinfo_encoding: Option<String>, // e.g. "UTF-8", "UTF-16LE", "windows-1252"
+    modinfo_name: Option<String>, // <Name>, with LOC_ keys resolved in en_US
+    modinfo_description: Option<String>,
 }
 
-/// XML struct for parsing .modinfo using serde
-#[derive(Debug, Deserialize)]
-#[serde(rename = "Mod")]
-pub struct ModXml {
-    #[serde(rename = "@id")]
-    pub id: Option<String>,
-    #[serde(rename = "Properties")]
-    pub properties: Properties,
-}
-
-// Using this temporarly until we get the `modinfo-parser` lib integrated
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "PascalCase")]
-pub struct Properties {
-    pub civ_mods_internal_version_id: Option<String>,
-}
-
-// Make sure this is aligned with the ignore list in the backend in Node.js
 fn is_entry_hidden(entry: &walkdir::DirEntry) -> bool {
-    let file_name = entry.file_name().to_string_lossy();
-    return file_name.starts_with(".")
-        || file_name.eq_ignore_ascii_case("__MACOSX")
-        || file_name.eq_ignore_ascii_case("thumbs.db");
+    is_hidden_file_name(&entry.file_name().to_string_lossy())
 }
 
 /// Computes SHA-256 hash of all files inside a directory,
@@ -95,70 +80,32 @@ fn read_file_to_vec(path: &Path) -> Result<Vec<u8>, String> {
     Ok(buffer)
 }
 
-/// Parses the .modinfo XML and extracts the `id` attribute
-fn extract_mod_xml(modinfo_path: &str) -> Option<ModXml> {
-    let mut buffer = vec![];
-    sanitize_xml(File::open(modinfo_path).ok()?, &mut buffer).ok()?;
-    let sanitized = String::from_utf8_lossy(&buffer).to_string();
-
-    let mod_xml: Result<ModXml, _> = quick_xml::de::from_str(&sanitized);
-    match mod_xml {
-        Ok(xml) => Some(xml),
+/// Parses the .modinfo XML, logging and discarding any parse failure
+fn extract_mod_xml(modinfo_path: &str) -> Option<ParsedMod> {
+    match Mod::open_detailed(modinfo_path) {
+        Ok(parsed) => Some(parsed),
+        Err(ModinfoError::Io(err)) => {
+            log::error!("Failed to read modinfo file {modinfo_path}: {err}");
+            None
+        }
         Err(err) => {
-            log::error!("Failed to parse modinfo XML: {err}");
+            log::error!("Failed to parse modinfo XML {modinfo_path}: {err}");
             None
         }
     }
 }
 
-fn sanitize_xml(reader: impl Read, writer: impl Write) -> quick_xml::Result<()> {
-    use quick_xml::{
-        errors::{Error, IllFormedError},
-        events::{BytesEnd, Event},
-        Reader, Writer,
-    };
-
-    let mut reader = Reader::from_reader(BufReader::new(reader));
-    let mut writer = Writer::new(writer);
-
-    let mut buffer = Vec::new();
-    loop {
-        let event = match reader.read_event_into(&mut buffer) {
-            Ok(Event::Eof) => return Ok(()),
-            Ok(Event::Text(text)) => {
-                // If we fail to unescape, it means it's not a valid XML text node
-                // and we should escape it instead.
-                let unescaped = text.unescape().unwrap_or_else(|_| {
-                    log::warn!("Illegal XML text node, escaping instead: {:?}", text);
-                    Cow::Owned(String::from_utf8_lossy(&text.into_inner()).to_string())
-                });
-
-                let escaped = escape::escape(unescaped);
-                let bytes_text = BytesText::from_escaped(escaped.into_owned());
-                Event::Text(bytes_text)
-            }
-            Ok(event) => event,
-            Err(Error::IllFormed(IllFormedError::MismatchedEndTag { expected, found })) => {
-                log::warn!("Mismatched end tag: expected: {expected:?}, found: {found:?}");
-                Event::End(BytesEnd::new(expected))
-            }
-            Err(Error::IllFormed(IllFormedError::MissingEndTag(tag))) => {
-                log::warn!("Missing end tag: {tag:?}");
-                Event::End(BytesEnd::new(tag))
-            }
-            Err(Error::IllFormed(IllFormedError::UnmatchedEndTag(tag))) => {
-                log::warn!("Unmatched end tag: {tag:?}");
-                Event::End(BytesEnd::new(tag))
-            }
-            Err(err) => return Err(err),
-        };
-        writer.write_event(event)?;
-        buffer.clear();
-    }
+/// Reads only the mod id and CivMods properties of a modinfo that doesn't
+/// match the full schema, so that the mod is still tracked.
+fn extract_mod_identity(modinfo_path: &str) -> ModIdentity {
+    ModIdentity::open(modinfo_path).unwrap_or_else(|err| {
+        log::error!("Failed to read mod id from {modinfo_path}: {err}");
+        ModIdentity::default()
+    })
 }
 
 /// Finds the `.modinfo` file inside a given directory.
-pub fn find_modinfo_file(directory: &Path) -> (Option<String>, Option<ModXml>) {
+pub fn find_modinfo_file(directory: &Path) -> (Option<String>, Option<ParsedMod>) {
     if let Some(entry) = WalkDir::new(directory)
         .into_iter()
         .filter_map(Result::ok)
@@ -181,17 +128,54 @@ pub fn find_modinfo_file(directory: &Path) -> (Option<String>, Option<ModXml>) {
     (None, None)
 }
 
-/// Scans the Civ7 Mods directory and returns a list of `ModInfo`.
-#[tauri::command]
-pub fn scan_civ_mods(mods_folder_path: Option<String>) -> Result<Vec<ModInfo>, String> {
-    if mods_folder_path.is_none() {
-        return Err("Mods folder path is missing. Set it in the Settings".to_string());
-    }
-
-    let mods_folder = Path::new(mods_folder_path.as_ref().unwrap());
+/// Checks the Mods folder path set in the Settings.
+fn mods_folder(mods_folder_path: &Option<String>) -> Result<&Path, String> {
+    let mods_folder = match mods_folder_path {
+        Some(path) => Path::new(path),
+        None => return Err("Mods folder path is missing. Set it in the Settings".to_string()),
+    };
     if !mods_folder.exists() || !mods_folder.is_dir() {
         return Err("Invalid Mods folder path".to_string());
     }
+    Ok(mods_folder)
+}
+
+/// An installed mod with a readable modinfo, for analyses across mods.
+pub struct InstalledMod {
+    pub folder_name: String,
+    pub modinfo_path: String,
+    pub modinfo: Mod,
+}
+
+/// Parses the modinfo of every mod in the Mods folder, sorted by folder name.
+/// Mods without a readable modinfo are skipped, as the game ignores them too.
+pub fn read_installed_mods(mods_folder_path: Option<String>) -> Result<Vec<InstalledMod>, String> {
+    let mods_folder = mods_folder(&mods_folder_path)?;
+
+    let mut installed = Vec::new();
+    for entry in
+        fs::read_dir(mods_folder).map_err(|e| format!("Failed to read mods directory: {}", e))?
+    {
+        let entry = entry.map_err(|e| format!("Error reading entry: {}", e))?;
+        if !entry.path().is_dir() {
+            continue;
+        }
+        if let (Some(modinfo_path), Some(parsed)) = find_modinfo_file(&entry.path()) {
+            installed.push(InstalledMod {
+                folder_name: entry.file_name().to_string_lossy().to_string(),
+                modinfo_path,
+                modinfo: parsed.modinfo,
+            });
+        }
+    }
+    installed.sort_by(|a, b| a.folder_name.cmp(&b.folder_name));
+    Ok(installed)
+}
+
+/// Scans the Civ7 Mods directory and returns a list of `ModInfo`.
+#[tauri::command]
+pub fn scan_civ_mods(mods_folder_path: Option<String>) -> Result<Vec<ModInfo>, String> {
+    let mods_folder = mods_folder(&mods_folder_path)?;
 
     let mut mods_list = Vec::new();
 
@@ -206,7 +190,15 @@ pub fn scan_civ_mods(mods_folder_path: Option<String>) -> Result<Vec<ModInfo>, S
                 .file_name()
                 .into_string()
                 .unwrap_or_else(|_| "Unknown Mod".to_string());
-            let (modinfo_path, modinfo_xml) = find_modinfo_file(&mod_dir);
+            let (modinfo_path, parsed) = find_modinfo_file(&mod_dir);
+            let (modinfo_xml, modinfo_repairs, modinfo_encoding) = match parsed {
+                Some(parsed) => (
+                    Some(parsed.modinfo),
+                    parsed.repairs,
+                    Some(parsed.encoding.name().to_string()),
+                ),
+                None => (None, vec![], None),
+            };
 
             // Skip mod if modinfo file is not found
             let modinfo_path_str = match modinfo_path.as_deref() {
@@ -224,13 +216,33 @@ pub fn scan_civ_mods(mods_folder_path: Option<String>) -> Result<Vec<ModInfo>, S
             let folder_hash = compute_folder_hash(modinfo_folder)
                 .unwrap_or_else(|_| "<unable to compute folder hash>".to_string());
 
+            let identity = match &modinfo_xml {
+                Some(xml) => ModIdentity::from(xml),
+                None => extract_mod_identity(modinfo_path_str),
+            };
+
+            let strings = modinfo_xml
+                .as_ref()
+                .map(|xml| xml.load_text(modinfo_folder))
+                .unwrap_or_default();
+            let modinfo_name = modinfo_xml
+                .as_ref()
+                .and_then(|xml| xml.localized_name(&strings, DEFAULT_LOCALE))
+                .map(str::to_string);
+            let modinfo_description = modinfo_xml
+                .as_ref()
+                .and_then(|xml| xml.localized_description(&strings, DEFAULT_LOCALE))
+                .map(str::to_string);
+
             mods_list.push(ModInfo {
                 mod_name,
                 modinfo_path: modinfo_path_str.to_string(),
-                modinfo_id: modinfo_xml.as_ref().and_then(|xml| xml.id.clone()),
-                civmods_internal_version_id: modinfo_xml
-                    .as_ref()
-                    .and_then(|xml| xml.properties.civ_mods_internal_version_id.clone()),
+                modinfo_id: identity.id,
+                civmods_internal_version_id: identity.civmods_internal_version_id,
+                modinfo_repairs,
+                modinfo_encoding,
+                modinfo_name,
+                modinfo_description,
                 folder_hash,
                 // Only the folder name without the full path
                 // Should be the same as mod_name for now
@@ -242,6 +254,36 @@ pub fn scan_civ_mods(mods_folder_path: Option<String>) -> Result<Vec<ModInfo>, S
     Ok(mods_list)
 }
 
+/// Conflicts between the installed mods, grouped by kind.
+#[derive(Serialize)]
+pub struct ModConflicts {
+    /// Virtual paths imported by several mods: only the winner's file is used.
+    files: Vec<FileConflict>,
+    /// Database rows changed by several mods: the last change wins.
+    database: Vec<DatabaseConflict>,
+    /// LOC_ keys defined by several mods in the same locale: the last one wins.
+    text: Vec<TextConflict>,
+}
+
+/// Detects installed mods which silently break each other.
+#[tauri::command]
+pub fn detect_mod_conflicts(mods_folder_path: Option<String>) -> Result<ModConflicts, String> {
+    let installed = read_installed_mods(mods_folder_path)?;
+    let mods = installed.iter().map(|installed| &installed.modinfo);
+    let with_folders: Vec<(&Mod, &Path)> = installed
+        .iter()
+        .filter_map(|installed| {
+            let folder = Path::new(&installed.modinfo_path).parent()?;
+            Some((&installed.modinfo, folder))
+        })
+        .collect();
+    Ok(ModConflicts {
+        files: detect_file_conflicts(mods),
+        database: detect_database_conflicts(with_folders.iter().copied()),
+        text: detect_text_conflicts(with_folders.iter().copied()),
+    })
+}
+
 #[tauri::command]
 pub fn get_unlocked_mod_folders(
     mods_folder_path: Option<String>,
@@ -269,42 +311,90 @@ pub fn get_unlocked_mod_folders(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::tempdir;
 
     #[test]
-    fn test_sanitize_xml() {
-        let xml = r#"<Item>ui/shell/extras/screen-extras.js</item>"#;
-        let mut output = vec![];
-        sanitize_xml(xml.as_bytes(), &mut output).unwrap();
-        assert_eq!(
-            String::from_utf8_lossy(&output),
-            r#"<Item>ui/shell/extras/screen-extras.js</Item>"#,
-        );
+    fn test_find_modinfo_file() {
+        let dir = tempdir().unwrap();
+        let modinfo_path = dir.path().join("a_mod.modinfo");
+        // NOTE: This is not a valid ModInfo XML
+        fs::write(
+            &modinfo_path,
+            r#"<Mod id="a_mod"><Item>ui/shell/extras/screen-extras.js</item></Mod>"#,
+        )
+        .unwrap();
+
+        let (path, mod_xml) = find_modinfo_file(dir.path());
+        assert_eq!(path, Some(modinfo_path.to_string_lossy().to_string()));
+        let parsed = mod_xml.unwrap();
+        assert_eq!(parsed.modinfo.id, "a_mod");
+        assert_eq!(parsed.repairs.len(), 1);
+        assert_eq!(parsed.encoding.name(), "UTF-8");
     }
 
     #[test]
-    fn test_parse_mod_xml() {
-        // NOTE: This is not a valid ModInfo XML
-        let xml = r#"<Mod id="a_mod"><Item>ui/shell/extras/screen-extras.js</item></Mod>"#;
+    fn test_scan_schema_invalid_modinfo() {
+        let dir = tempdir().unwrap();
+        let folder = dir.path().join("a_mod");
+        fs::create_dir(&folder).unwrap();
+        // NOTE: The action group is missing its id
+        fs::write(
+            folder.join("a_mod.modinfo"),
+            r#"<Mod id="a_mod"><Properties><CivModsInternalVersionId>42</CivModsInternalVersionId></Properties><ActionGroups><ActionGroup scope="game"/></ActionGroups></Mod>"#,
+        )
+        .unwrap();
+
+        let mods = scan_civ_mods(Some(dir.path().to_string_lossy().to_string())).unwrap();
+        assert_eq!(mods.len(), 1);
+        assert_eq!(mods[0].modinfo_id.as_deref(), Some("a_mod"));
+        assert_eq!(mods[0].civmods_internal_version_id.as_deref(), Some("42"));
+        assert_eq!(mods[0].modinfo_encoding, None);
+    }
 
-        let mut buffer = vec![];
-        sanitize_xml(xml.as_bytes(), &mut buffer).unwrap();
-        let sanitized = String::from_utf8_lossy(&buffer).to_string();
+    #[test]
+    fn test_detect_mod_conflicts() {
+        let dir = tempdir().unwrap();
+        for id in ["a_mod", "b_mod"] {
+            let folder = dir.path().join(id);
+            fs::create_dir(&folder).unwrap();
+            fs::write(
+                folder.join(format!("{id}.modinfo")),
+                format!(
+                    r#"<Mod id="{id}"><ActionGroups><ActionGroup id="ui" scope="game"><Actions><ImportFiles><Item>ui/screen.js</Item></ImportFiles></Actions></ActionGroup></ActionGroups></Mod>"#
+                ),
+            )
+            .unwrap();
+        }
 
-        let mod_xml: ModXml = quick_xml::de::from_str(&sanitized).unwrap();
-        assert_eq!(mod_xml.id, Some("a_mod".to_string()));
+        let conflicts =
+            detect_mod_conflicts(Some(dir.path().to_string_lossy().to_string())).unwrap();
+        assert_eq!(conflicts.files.len(), 1);
+        assert_eq!(conflicts.files[0].winner, "b_mod");
+        assert!(conflicts.files[0].ambiguous);
+        assert!(conflicts.database.is_empty());
+        assert!(conflicts.text.is_empty());
     }
 
     #[test]
     fn test_parse_ampersand_in_text() {
-        let xml =
-            r#"<Mod id="a_mod"><Properties><Name>Mod by me & friends</Name></Properties></Mod>"#;
-        let mut buffer = vec![];
-        sanitize_xml(xml.as_bytes(), &mut buffer).unwrap();
-        let sanitized = String::from_utf8_lossy(&buffer).to_string();
-
+        let dir = tempdir().unwrap();
+        let modinfo_path = dir.path().join("a_mod.modinfo");
+        fs::write(
+            &modinfo_path,
+            r#"<Mod id="a_mod"><Properties><Name>Mod by me & friends</Name><CivModsInternalVersionId>abc</CivModsInternalVersionId></Properties></Mod>"#,
+        )
+        .unwrap();
+
+        let mod_xml = extract_mod_xml(&modinfo_path.to_string_lossy())
+            .unwrap()
+            .modinfo;
+        assert_eq!(
+            mod_xml.properties.name.as_deref(),
+            Some("Mod by me & friends")
+        );
         assert_eq!(
-            sanitized,
-            r#"<Mod id="a_mod"><Properties><Name>Mod by me &amp; friends</Name></Properties></Mod>"#,
+            mod_xml.properties.civmods_internal_version_id.as_deref(),
+            Some("abc")
         );
     }
 }
@@ -0,0 +1,57 @@
+use modinfo_parser::{diff, Mod, ModDiff};
+
+/// Compares an installed mod with the update about to replace it, so the app
+/// can show what changes before applying it.
+#[tauri::command]
+pub fn diff_mod_update(
+    installed_modinfo_path: String,
+    update_modinfo_path: String,
+) -> Result<ModDiff, String> {
+    let open = |modinfo_path: &str| {
+        Mod::open(modinfo_path).map_err(|e| format!("Failed to read {modinfo_path}: {e}"))
+    };
+    let installed = open(&installed_modinfo_path)?;
+    let update = open(&update_modinfo_path)?;
+    let diff = diff(&installed, &update);
+    if diff.is_critical() {
+        log::warn!(
+            "Updating {} changes AffectsSavedGames to {:?}",
+            installed.id,
+            diff.affects_saved_games
+        );
+    }
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_diff_mod_update() {
+        let dir = tempdir().unwrap();
+        let installed_path = dir.path().join("installed.modinfo");
+        let update_path = dir.path().join("update.modinfo");
+        fs::write(
+            &installed_path,
+            r#"<Mod id="a_mod" version="1.0"><Properties><AffectsSavedGames>0</AffectsSavedGames></Properties></Mod>"#,
+        )
+        .unwrap();
+        fs::write(
+            &update_path,
+            r#"<Mod id="a_mod" version="1.1"><Properties><AffectsSavedGames>1</AffectsSavedGames></Properties></Mod>"#,
+        )
+        .unwrap();
+
+        let diff = diff_mod_update(
+            installed_path.to_string_lossy().to_string(),
+            update_path.to_string_lossy().to_string(),
+        )
+        .unwrap();
+        assert!(diff.is_critical());
+        assert_eq!(diff.affects_saved_games, Some(true));
+        assert!(diff.version.is_some());
+    }
+}
@@ -14,6 +14,40 @@ export interface ModInfo {
   folder_hash: string;
   folder_name: string;
   civmods_internal_version_id?: string;
+  /**
+   * Fixes applied while parsing a malformed modinfo XML
+   */
+  modinfo_repairs?: ModinfoRepair[];
+  /**
+   * Encoding of the modinfo file, e.g. `UTF-8` or `windows-1252`
+   */
+  modinfo_encoding?: string;
+  /**
+   * `<Name>` of the modinfo, with `LOC_` keys resolved in en_US
+   */
+  modinfo_name?: string;
+  /**
+   * `<Description>` of the modinfo, with `LOC_` keys resolved in en_US
+   */
+  modinfo_description?: string;
+}
+
+export interface ModinfoRepair {
+  kind:
+    | 'MismatchedEndTag'
+    | 'MissingEndTag'
+    | 'UnmatchedEndTag'
+    | 'UnescapedText'
+    | 'UnescapedLessThan'
+    | 'DuplicateAttribute'
+    | 'UnquotedAttributeValue'
+    | 'MalformedAttribute'
+    | 'LeadingContent'
+    | 'TrailingContent'
+    | 'InvalidByteSequence';
+  position: { offset: number; line: number; column: number };
+  original: string;
+  replacement: string;
 }
 
 export type ModDependency = {
@@ -84,7 +84,12 @@ export function ModBox(props: IModBoxProps) {
 
   const [loading, setLoading] = useState(false);
 
-  const { install, uninstall, mods } = useModsContext();
+  const { install, uninstall, mods, compatibility } = useModsContext();
+  const modCompatibility = compatibility?.mods.find(
+    (c) =>
+      c.folder_name === local?.folder_name &&
+      c.modinfo_id === local?.modinfo_id
+  );
 
   const latestVersion = getLatestVersionMatchingLocal(fetched, local);
   const isLatest = latestVersion && isSameVersion(latestVersion, local);
@@ -296,6 +301,24 @@ export function ModBox(props: IModBoxProps) {
                         </Text>
                       </Tooltip>
                     )}
+                    {compatibility &&
+                      modCompatibility?.compatible === false && (
+                        <Tooltip
+                          color="dark.8"
+                          multiline
+                          w={320}
+                          label={`This mod is declared compatible with ${modCompatibility.compatibility}, which doesn't include the installed game version ${compatibility.game_version}.`}
+                        >
+                          <Text
+                            c="orange.1"
+                            fz={'0.85rem'}
+                            className={styles.descriptionBlock}
+                          >
+                            <IconAlertHexagon size={12} /> Not for{' '}
+                            {compatibility.game_version}
+                          </Text>
+                        </Tooltip>
+                      )}
                   </Group>
                   <Group gap={4} align="flex-start" mr="md">
                     {mod.fetched?.downloads_count && (
@@ -16,7 +16,11 @@ import { installMod, uninstallMod } from './installMod';
 import { notifications } from '@mantine/notifications';
 import { open } from '@tauri-apps/plugin-dialog';
 import { getActiveModsFolder } from './getModsFolder';
-import { invokeScanCivMods } from './commands/modsRustBindings';
+import {
+  GameCompatibility,
+  invokeCheckModsCompatibility,
+  invokeScanCivMods,
+} from './commands/modsRustBindings';
 import { computeModsData } from './commands/computeModsData';
 import { getVersion } from '@tauri-apps/api/app';
 import { installModDependencies } from './dependencies/installModDependencies';
@@ -38,6 +42,8 @@ export type ModsContextType = {
   isFetching: boolean;
   isLoadingInstalled: boolean;
   lastFetch: Date | null;
+  /** `null` until checked, or when the game version cannot be detected */
+  compatibility: GameCompatibility | null;
 };
 
 export type InstallModContextOptions = {
@@ -52,6 +58,8 @@ export function ModsContextProvider(props: { children: React.ReactNode }) {
 
   const [isLoadingInstalled, setIsLoadingInstalled] = useState(false);
   const [modsInfo, setModsInfo] = useState<ModInfo[]>([]);
+  const [compatibility, setCompatibility] =
+    useState<GameCompatibility | null>(null);
   const [reloadIndex, setReloadIndex] = useState(0);
   const [lastFetch, setLastFetch] = useState<Date | null>(null);
 
@@ -88,6 +96,14 @@ export function ModsContextProvider(props: { children: React.ReactNode }) {
         });
       }
       setIsLoadingInstalled(false);
+
+      // Flags mods not declared compatible with the installed game, e.g. after a patch
+      try {
+        setCompatibility(await invokeCheckModsCompatibility(folder!));
+      } catch (error) {
+        console.warn('Failed to check mods compatibility:', error);
+        setCompatibility(null);
+      }
     }
 
     findMods().catch(console.error);
@@ -275,6 +291,7 @@ export function ModsContextProvider(props: { children: React.ReactNode }) {
       getModsFolder,
       fetchedMods,
       lastFetch,
+      compatibility,
     }),
     [
       mods,
@@ -287,6 +304,7 @@ export function ModsContextProvider(props: { children: React.ReactNode }) {
       getModsFolder,
       fetchedMods,
       lastFetch,
+      compatibility,
     ]
   );
 
@@ -141,7 +141,11 @@ export function computeModsData(options: ComputeModsDataOptions): ModData[] {
       installedVersion: undefined,
       isUnknown: true,
       isLocalOnly: true,
-      name: info.modinfo_id ?? info.folder_name ?? 'Unknown mod',
+      name:
+        info.modinfo_name ??
+        info.modinfo_id ??
+        info.folder_name ??
+        'Unknown mod',
       modinfo_id: info.modinfo_id,
       dependedBy: [],
       dependsOn: [],
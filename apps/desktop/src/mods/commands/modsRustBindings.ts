@@ -46,6 +46,202 @@ export async function invokeScanCivMods(modsFolderPath: string) {
   });
 }
 
+export interface FileOverride {
+  mod_id: string;
+  action_group: string;
+  action: string;
+  path: string;
+  load_order: number;
+}
+
+export interface FileConflict {
+  scope: string;
+  /** Lowercase virtual path */
+  path: string;
+  /** In load order, the last one wins */
+  overrides: FileOverride[];
+  winner: string;
+  /** The winner shares its load order with another mod */
+  ambiguous: boolean;
+}
+
+export interface DatabaseTouch {
+  mod_id: string;
+  action_group: string;
+  path: string;
+  load_order: number;
+  operation: 'Insert' | 'Update' | 'Delete';
+  /** The file changes the whole table rather than this row */
+  table_wide: boolean;
+}
+
+export interface DatabaseConflict {
+  scope: string;
+  table: string;
+  /** Primary key of the row, or null for the whole table */
+  key: string | null;
+  /** In load order, later changes override earlier ones */
+  touches: DatabaseTouch[];
+}
+
+export interface TextDefinition {
+  mod_id: string;
+  /** Null for `<LocalizedText>` entries */
+  action_group: string | null;
+  path: string;
+  load_order: number;
+  text: string;
+}
+
+export interface TextConflict {
+  locale: string;
+  tag: string;
+  /** In load order, the last one is shown in game */
+  definitions: TextDefinition[];
+  winner: string;
+}
+
+export interface ModConflicts {
+  files: FileConflict[];
+  database: DatabaseConflict[];
+  text: TextConflict[];
+}
+
+/**
+ * Invokes `detect_mod_conflicts` to find installed mods which override each other.
+ * @param modsFolderPath The mods folder path.
+ */
+export async function invokeDetectModConflicts(modsFolderPath: string) {
+  return await invoke<ModConflicts>('detect_mod_conflicts', {
+    modsFolderPath,
+  });
+}
+
+export interface MissingModDependency {
+  mod_id: string;
+  dependency_id: string;
+}
+
+export interface ModDependencies {
+  /** Mod ids, each after the mods it depends on */
+  order: string[];
+  missing: MissingModDependency[];
+  cycles: string[][];
+  /** Mods no other installed mod depends on */
+  top_level: string[];
+  dot: string;
+  mermaid: string;
+}
+
+/**
+ * Invokes `resolve_mod_dependencies` to check the dependencies between the installed mods.
+ * @param modsFolderPath The mods folder path.
+ * @param gameFolderPath The game install folder, to know the modules of its DLCs.
+ * @returns Missing and circular dependencies, with the graph as DOT and Mermaid.
+ */
+export async function invokeResolveModDependencies(
+  modsFolderPath: string,
+  gameFolderPath?: string,
+) {
+  return await invoke<ModDependencies>('resolve_mod_dependencies', {
+    modsFolderPath,
+    gameFolderPath,
+  });
+}
+
+export interface LocaleCoverage {
+  locale: string;
+  /** Number of en_US keys translated in the locale */
+  present: number;
+  /** Number of en_US keys */
+  total: number;
+  /** en_US keys missing from the locale */
+  missing: string[];
+}
+
+/**
+ * Invokes `get_translation_coverage` to compare each locale of a mod with en_US.
+ * @param modinfoPath Path of the mod's `.modinfo` file.
+ */
+export async function invokeGetTranslationCoverage(modinfoPath: string) {
+  return await invoke<LocaleCoverage[]>('get_translation_coverage', {
+    modinfoPath,
+  });
+}
+
+export interface ModCompatibility {
+  folder_name: string;
+  modinfo_id: string;
+  compatibility: string | null;
+  range: string | null;
+  compatible: boolean | null;
+}
+
+export interface GameCompatibility {
+  /** Version of the installed game, read from its executable */
+  game_version: string;
+  mods: ModCompatibility[];
+}
+
+/**
+ * Invokes `check_mods_compatibility` to flag installed mods that are not
+ * declared compatible with the version of the installed game.
+ * @param modsFolderPath The mods folder path.
+ * @param gameFolderPath The game install folder, found in the default Steam library when omitted.
+ */
+export async function invokeCheckModsCompatibility(
+  modsFolderPath: string,
+  gameFolderPath?: string,
+) {
+  return await invoke<GameCompatibility>('check_mods_compatibility', {
+    modsFolderPath,
+    gameFolderPath,
+  });
+}
+
+export type ModChange = 'Added' | 'Removed' | 'Changed';
+
+export interface ModDiff {
+  /** `null` when both versions are equal, even if written differently. */
+  version: {
+    old: string | null;
+    new: string | null;
+    direction: 'Upgrade' | 'Downgrade' | null;
+  } | null;
+  /** The new `AffectsSavedGames` value when it flipped, which breaks saves. */
+  affects_saved_games: boolean | null;
+  properties: { name: string; old: string | null; new: string | null }[];
+  dependencies: {
+    id: string;
+    kind: 'Required' | 'Optional';
+    change: ModChange;
+  }[];
+  criteria: { id: string; change: ModChange }[];
+  action_groups: { id: string; change: ModChange }[];
+  files: {
+    action_group: string;
+    action: string;
+    path: string;
+    locale: string | null;
+    change: ModChange;
+  }[];
+}
+
+/**
+ * Invokes `diff_mod_update` to show what an update changes before applying it.
+ * @param installedModinfoPath Path of the installed mod's `.modinfo` file.
+ * @param updateModinfoPath Path of the update's `.modinfo` file.
+ */
+export async function invokeDiffModUpdate(
+  installedModinfoPath: string,
+  updateModinfoPath: string,
+) {
+  return await invoke<ModDiff>('diff_mod_update', {
+    installedModinfoPath,
+    updateModinfoPath,
+  });
+}
+
 export interface CivModsProperties {
   target_modinfo_id: string | undefined;
   target_modinfo_path: string | undefined;
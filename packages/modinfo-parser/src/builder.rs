@@ -0,0 +1,351 @@
+use crate::Mod;
+use crate::schema::{
+    ActionCriterion, ActionGroup, ActionGroupActions, ActionGroupProperties, ActionItem,
+    ActionKind, CriterionCondition, Dependency, Properties,
+};
+use crate::validate::{Diagnostic, Severity};
+use std::fmt;
+
+/// Why [`ModBuilder::build`] refused to build a mod.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    /// A value that cannot be stored in its property, e.g. a non numeric
+    /// `AffectsSavedGames`.
+    InvalidProperty { name: String, value: String },
+    /// The mod has errors, as reported by [`Mod::validate`].
+    Invalid(Vec<Diagnostic>),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::InvalidProperty { name, value } => {
+                write!(f, "Invalid value '{value}' for property {name}")
+            }
+            BuildError::Invalid(diagnostics) => {
+                let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+                write!(f, "Invalid mod: {}", messages.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Fluent builder for [`Mod`], see [`Mod::builder`].
+#[derive(Debug)]
+pub struct ModBuilder {
+    modinfo: Mod,
+    error: Option<BuildError>,
+}
+
+impl Mod {
+    /// Starts a new mod, with the `ModInfo` namespace and `AffectsSavedGames`
+    /// enabled, as the game assumes when the property is missing.
+    pub fn builder(id: impl Into<String>) -> ModBuilder {
+        ModBuilder {
+            modinfo: Mod {
+                id: id.into(),
+                version: None,
+                xmlns: Some("ModInfo".to_string()),
+                properties: Properties {
+                    affects_saved_games: true,
+                    ..Default::default()
+                },
+                dependencies: vec![],
+                references: vec![],
+                localized_text: vec![],
+                action_criteria: vec![],
+                action_groups: vec![],
+            },
+            error: None,
+        }
+    }
+}
+
+impl ModBuilder {
+    /// The `version` attribute of `<Mod>`.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.modinfo.version = Some(version.into());
+        self
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.modinfo.properties.name = Some(name.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.modinfo.properties.description = Some(description.into());
+        self
+    }
+
+    pub fn authors(mut self, authors: impl Into<String>) -> Self {
+        self.modinfo.properties.authors = Some(authors.into());
+        self
+    }
+
+    pub fn affects_saved_games(mut self, affects_saved_games: bool) -> Self {
+        self.modinfo.properties.affects_saved_games = affects_saved_games;
+        self
+    }
+
+    /// Sets any property, see [`Properties::set`].
+    pub fn property(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        if let Err(err) = self.modinfo.properties.set(name, value) {
+            self.error.get_or_insert(err);
+        }
+        self
+    }
+
+    pub fn dependency(mut self, id: impl Into<String>, title: Option<&str>) -> Self {
+        self.modinfo.dependencies.push(Dependency {
+            id: id.into(),
+            title: title.map(str::to_string),
+        });
+        self
+    }
+
+    /// An optional dependency, listed under `<References>`.
+    pub fn reference(mut self, id: impl Into<String>, title: Option<&str>) -> Self {
+        self.modinfo.references.push(Dependency {
+            id: id.into(),
+            title: title.map(str::to_string),
+        });
+        self
+    }
+
+    /// Criteria met when all of `conditions` are met.
+    pub fn criteria(
+        mut self,
+        id: impl Into<String>,
+        conditions: impl IntoIterator<Item = CriterionCondition>,
+    ) -> Self {
+        self.modinfo.action_criteria.push(ActionCriterion {
+            id: id.into(),
+            any: false,
+            conditions: conditions.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Criteria met when any of `conditions` is met.
+    pub fn any_criteria(
+        mut self,
+        id: impl Into<String>,
+        conditions: impl IntoIterator<Item = CriterionCondition>,
+    ) -> Self {
+        self.modinfo.action_criteria.push(ActionCriterion {
+            id: id.into(),
+            any: true,
+            conditions: conditions.into_iter().collect(),
+        });
+        self
+    }
+
+    pub fn action_group(mut self, group: ActionGroupBuilder) -> Self {
+        self.modinfo.action_groups.push(group.build());
+        self
+    }
+
+    /// A `<LocalizedText><File>` entry.
+    pub fn localized_text(mut self, path: impl Into<String>) -> Self {
+        self.modinfo.localized_text.push(path.into());
+        self
+    }
+
+    /// Returns the mod unless it has errors. Warnings are only logged.
+    pub fn build(self) -> Result<Mod, BuildError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let (errors, warnings): (Vec<Diagnostic>, Vec<Diagnostic>) = self
+            .modinfo
+            .validate()
+            .into_iter()
+            .partition(|diagnostic| diagnostic.severity == Severity::Error);
+        for warning in warnings {
+            log::warn!("{}: {warning}", self.modinfo.id);
+        }
+        if !errors.is_empty() {
+            return Err(BuildError::Invalid(errors));
+        }
+        Ok(self.modinfo)
+    }
+}
+
+/// Fluent builder for [`ActionGroup`], see [`ActionGroup::builder`].
+#[derive(Debug)]
+pub struct ActionGroupBuilder {
+    group: ActionGroup,
+}
+
+impl ActionGroup {
+    /// Starts an action group for `scope`, `game` or `shell`.
+    pub fn builder(id: impl Into<String>, scope: impl Into<String>) -> ActionGroupBuilder {
+        ActionGroupBuilder {
+            group: ActionGroup {
+                id: id.into(),
+                scope: Some(scope.into()),
+                criteria: None,
+                properties: ActionGroupProperties::default(),
+                actions: vec![],
+            },
+        }
+    }
+}
+
+impl ActionGroupBuilder {
+    /// Id of the `<Criteria>` that enables the group.
+    pub fn criteria(mut self, criteria: impl Into<String>) -> Self {
+        self.group.criteria = Some(criteria.into());
+        self
+    }
+
+    pub fn load_order(mut self, load_order: i64) -> Self {
+        self.group.properties.load_order = Some(load_order);
+        self
+    }
+
+    fn push(&mut self, kind: ActionKind, item: ActionItem) {
+        if self.group.actions.is_empty() {
+            self.group.actions.push(ActionGroupActions::default());
+        }
+        self.group.actions[0].push_item(kind, item);
+    }
+
+    /// Adds `path` to the `kind` action, e.g. `UpdateDatabase`.
+    pub fn action(mut self, kind: ActionKind, path: impl Into<String>) -> Self {
+        self.push(
+            kind,
+            ActionItem {
+                path: path.into(),
+                locale: None,
+            },
+        );
+        self
+    }
+
+    /// Adds `path` to the `kind` action, only loaded for `locale`.
+    pub fn localized_action(
+        mut self,
+        kind: ActionKind,
+        path: impl Into<String>,
+        locale: impl Into<String>,
+    ) -> Self {
+        self.push(
+            kind,
+            ActionItem {
+                path: path.into(),
+                locale: Some(locale.into()),
+            },
+        );
+        self
+    }
+
+    pub fn build(self) -> ActionGroup {
+        self.group
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::DiagnosticKind;
+    use crate::write::WriteOptions;
+    use indoc::indoc;
+
+    #[test]
+    fn test_build_mod() -> anyhow::Result<()> {
+        let built = Mod::builder("compat-patch")
+            .version("1")
+            .name("Compatibility patch")
+            .authors("Me & you")
+            .affects_saved_games(false)
+            .property("Package", "Mod")
+            .dependency("base-standard", Some("LOC_MODULE_BASE_STANDARD_NAME"))
+            .reference("other-mod", None)
+            .criteria(
+                "antiquity",
+                [CriterionCondition::AgeInUse("AGE_ANTIQUITY".into())],
+            )
+            .action_group(
+                ActionGroup::builder("game", "game")
+                    .criteria("antiquity")
+                    .load_order(100)
+                    .action(ActionKind::UpdateDatabase, "data/patch.sql")
+                    .localized_action(ActionKind::UpdateText, "text/de/Text.xml", "de_DE"),
+            )
+            .localized_text("text/en_us/Text.xml")
+            .build()?;
+
+        assert_eq!(
+            built.to_string_pretty(&WriteOptions::default())?,
+            indoc! {r#"
+                <?xml version="1.0" encoding="utf-8"?>
+                <Mod id="compat-patch" version="1" xmlns="ModInfo">
+                	<Properties>
+                		<Name>Compatibility patch</Name>
+                		<Authors>Me &amp; you</Authors>
+                		<Package>Mod</Package>
+                		<AffectsSavedGames>0</AffectsSavedGames>
+                	</Properties>
+                	<Dependencies>
+                		<Mod id="base-standard" title="LOC_MODULE_BASE_STANDARD_NAME"/>
+                	</Dependencies>
+                	<References>
+                		<Mod id="other-mod"/>
+                	</References>
+                	<LocalizedText>
+                		<File>text/en_us/Text.xml</File>
+                	</LocalizedText>
+                	<ActionCriteria>
+                		<Criteria id="antiquity">
+                			<AgeInUse>AGE_ANTIQUITY</AgeInUse>
+                		</Criteria>
+                	</ActionCriteria>
+                	<ActionGroups>
+                		<ActionGroup id="game" scope="game" criteria="antiquity">
+                			<Properties>
+                				<LoadOrder>100</LoadOrder>
+                			</Properties>
+                			<Actions>
+                				<UpdateDatabase>
+                					<Item>data/patch.sql</Item>
+                				</UpdateDatabase>
+                				<UpdateText>
+                					<Item locale="de_DE">text/de/Text.xml</Item>
+                				</UpdateText>
+                			</Actions>
+                		</ActionGroup>
+                	</ActionGroups>
+                </Mod>
+            "#}
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_invalid_mod() {
+        let error = Mod::builder("a_mod")
+            .action_group(ActionGroup::builder("game", "game").criteria("missing"))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            BuildError::Invalid(vec![
+                DiagnosticKind::UndefinedCriteria {
+                    action_group: "game".to_string(),
+                    criteria: "missing".to_string(),
+                }
+                .into()
+            ])
+        );
+
+        let error = Mod::builder("a_mod")
+            .property("AffectsSavedGames", "yes")
+            .build()
+            .unwrap_err();
+        assert!(matches!(error, BuildError::InvalidProperty { .. }));
+    }
+}
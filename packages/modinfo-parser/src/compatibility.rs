@@ -0,0 +1,278 @@
+use crate::Mod;
+use crate::text::{DEFAULT_LOCALE, LocalizedStrings};
+use crate::version::{ModVersion, VersionScheme};
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+/// Game versions a mod declares to work with, parsed from its
+/// `<Compatibility>` property.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameVersionRange {
+    pub min: Bound<ModVersion>,
+    pub max: Bound<ModVersion>,
+}
+
+/// A version token of a compatibility text, e.g. `1.1.2` or `1.1.x`.
+struct VersionToken {
+    version: String,
+    wildcard: bool,
+    /// Byte range in the text.
+    start: usize,
+    end: usize,
+}
+
+fn version_tokens(text: &str) -> Vec<VersionToken> {
+    let mut tokens = vec![];
+    let bytes = text.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        // Digits start a version at the start of a word, also after a `v`.
+        let starts_word = |at: usize| at == 0 || !bytes[at - 1].is_ascii_alphanumeric();
+        let starts_word = match index.checked_sub(1).map(|previous| bytes[previous]) {
+            None => true,
+            Some(b'v' | b'V') => starts_word(index - 1),
+            Some(b'.') => false,
+            Some(_) => starts_word(index),
+        };
+        if !bytes[index].is_ascii_digit() || !starts_word {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        let mut wildcard = false;
+        while index < bytes.len() {
+            let byte = bytes[index];
+            let next_is_part = bytes
+                .get(index + 1)
+                .is_some_and(|next| next.is_ascii_digit() || matches!(next, b'x' | b'X' | b'*'));
+            if byte.is_ascii_digit() {
+                index += 1;
+            } else if byte == b'.' && next_is_part && !wildcard {
+                index += 1;
+                if matches!(bytes[index], b'x' | b'X' | b'*') {
+                    wildcard = true;
+                    index += 1;
+                }
+            } else {
+                break;
+            }
+        }
+        let version = text[start..index].trim_end_matches(['.', 'x', 'X', '*']);
+        tokens.push(VersionToken {
+            version: version.to_string(),
+            wildcard,
+            start,
+            end: index,
+        });
+    }
+    tokens
+}
+
+/// The first version after every version starting with `version`, e.g. `1.2`
+/// for `1.1` and `1.1.3` for `1.1.2`.
+fn next_series(version: &str) -> ModVersion {
+    let mut components: Vec<u64> = match ModVersion::parse(version).scheme() {
+        VersionScheme::Numeric { components, .. } => components.clone(),
+        _ => vec![0],
+    };
+    if let Some(last) = components.last_mut() {
+        *last += 1;
+    }
+    let components: Vec<String> = components.iter().map(u64::to_string).collect();
+    ModVersion::parse(&components.join("."))
+}
+
+impl GameVersionRange {
+    /// Every version starting with `version`: `1.1` covers `1.1.0` and
+    /// `1.1.2`, but not `1.2`.
+    pub fn series(version: &str) -> Self {
+        Self {
+            min: Bound::Included(ModVersion::parse(version)),
+            max: Bound::Excluded(next_series(version)),
+        }
+    }
+
+    /// Reads the common ways authors write compatibility:
+    /// `1.1.2`, `1.1.x`, `>= 1.1`, `1.1+`, `1.1 or later`, `up to 1.2`,
+    /// `< 1.2`, `1.0.1 - 1.1.2` or `1.0 to 1.1`, within any surrounding text
+    /// such as `Game version 1.1.2`. Returns `None` without any version.
+    pub fn parse(text: &str) -> Option<Self> {
+        let tokens = version_tokens(text);
+        let first = tokens.first()?;
+        // ASCII only, so that the token offsets still apply.
+        let lower = text.to_ascii_lowercase();
+        let before = lower[..first.start].trim_end();
+        let after = lower[first.end..].trim_start();
+
+        if let Some(second) = tokens.get(1) {
+            let between = lower[first.end..second.start].trim();
+            if matches!(between, "-" | "–" | "to" | ".." | "through") {
+                let max = if second.wildcard {
+                    Bound::Excluded(next_series(&second.version))
+                } else {
+                    Bound::Included(ModVersion::parse(&second.version))
+                };
+                return Some(Self {
+                    min: Bound::Included(ModVersion::parse(&first.version)),
+                    max,
+                });
+            }
+        }
+
+        let version = ModVersion::parse(&first.version);
+        let at_least = before.ends_with(">=")
+            || before.ends_with('≥')
+            || before.ends_with("min")
+            || before.ends_with("minimum")
+            || before.ends_with("from")
+            || after.starts_with('+')
+            || after.starts_with("or later")
+            || after.starts_with("or newer")
+            || after.starts_with("or higher")
+            || after.starts_with("and above")
+            || after.starts_with("and later");
+        let at_most = before.ends_with("<=")
+            || before.ends_with('≤')
+            || before.ends_with("up to")
+            || before.ends_with("max")
+            || before.ends_with("maximum")
+            || after.starts_with("or earlier")
+            || after.starts_with("or older")
+            || after.starts_with("and below");
+
+        Some(if at_least {
+            Self {
+                min: Bound::Included(version),
+                max: Bound::Unbounded,
+            }
+        } else if at_most {
+            Self {
+                min: Bound::Unbounded,
+                max: Bound::Included(version),
+            }
+        } else if before.ends_with('>') {
+            Self {
+                min: Bound::Excluded(version),
+                max: Bound::Unbounded,
+            }
+        } else if before.ends_with('<') {
+            Self {
+                min: Bound::Unbounded,
+                max: Bound::Excluded(version),
+            }
+        } else {
+            Self::series(&first.version)
+        })
+    }
+}
+
+impl RangeBounds<ModVersion> for GameVersionRange {
+    fn start_bound(&self) -> Bound<&ModVersion> {
+        self.min.as_ref()
+    }
+
+    fn end_bound(&self) -> Bound<&ModVersion> {
+        self.max.as_ref()
+    }
+}
+
+impl fmt::Display for GameVersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let min = match &self.min {
+            Bound::Included(version) => Some(format!(">={version}")),
+            Bound::Excluded(version) => Some(format!(">{version}")),
+            Bound::Unbounded => None,
+        };
+        let max = match &self.max {
+            Bound::Included(version) => Some(format!("<={version}")),
+            Bound::Excluded(version) => Some(format!("<{version}")),
+            Bound::Unbounded => None,
+        };
+        match (min, max) {
+            (Some(min), Some(max)) => write!(f, "{min}, {max}"),
+            (Some(bound), None) | (None, Some(bound)) => f.write_str(&bound),
+            (None, None) => f.write_str("*"),
+        }
+    }
+}
+
+impl Mod {
+    /// The `<Compatibility>` property, with its `LOC_` key resolved in
+    /// [`DEFAULT_LOCALE`] using the mod's text, see [`Mod::load_text`].
+    pub fn compatibility_text<'s>(&'s self, strings: &'s LocalizedStrings) -> Option<&'s str> {
+        let compatibility = self.properties.compatibility.as_deref()?;
+        Some(strings.resolve(DEFAULT_LOCALE, compatibility))
+    }
+
+    /// The game versions the mod declares to work with, if it says so in a
+    /// way [`GameVersionRange::parse`] understands.
+    pub fn game_compatibility(&self, strings: &LocalizedStrings) -> Option<GameVersionRange> {
+        GameVersionRange::parse(self.compatibility_text(strings)?)
+    }
+
+    /// Whether the mod declares to work with `game_version`, `None` when it
+    /// doesn't declare anything usable.
+    pub fn is_compatible_with(
+        &self,
+        strings: &LocalizedStrings,
+        game_version: &ModVersion,
+    ) -> Option<bool> {
+        Some(self.game_compatibility(strings)?.contains(game_version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_compatibility() {
+        let cases = [
+            ("1.1.2", ">=1.1.2, <1.1.3"),
+            ("Game version 1.1", ">=1.1, <1.2"),
+            ("1.1.x", ">=1.1, <1.2"),
+            (">= 1.0.1", ">=1.0.1"),
+            ("v1.1+", ">=1.1"),
+            ("1.1.0 or later", ">=1.1.0"),
+            ("up to 1.2", "<=1.2"),
+            ("< 1.2", "<1.2"),
+            ("1.0.1 - 1.1.2", ">=1.0.1, <=1.1.2"),
+            ("1.0 to 1.1.*", ">=1.0, <1.2"),
+            ("ẞ 1.1", ">=1.1, <1.2"),
+            ("Kompatibel \u{212A} 1.1", ">=1.1, <1.2"),
+            ("ΩΩ >= 1.1", ">=1.1"),
+            ("Version 1.1 OR LATER", ">=1.1"),
+        ];
+        for (text, expected) in cases {
+            let range = GameVersionRange::parse(text).unwrap();
+            assert_eq!(range.to_string(), expected, "{text}");
+        }
+        assert_eq!(GameVersionRange::parse("All versions"), None);
+        assert_eq!(GameVersionRange::parse("LOC_MOD_COMPATIBILITY"), None);
+    }
+
+    #[test]
+    fn test_is_compatible_with() -> anyhow::Result<()> {
+        let modinfo = Mod::builder("a_mod")
+            .property("Compatibility", "LOC_A_MOD_COMPATIBILITY")
+            .build()?;
+        let mut strings = LocalizedStrings::default();
+        assert_eq!(
+            modinfo.is_compatible_with(&strings, &ModVersion::parse("1.1.0")),
+            None
+        );
+
+        strings.insert(
+            DEFAULT_LOCALE,
+            "LOC_A_MOD_COMPATIBILITY",
+            "Works with 1.1.x",
+        );
+        let compatible =
+            |version: &str| modinfo.is_compatible_with(&strings, &ModVersion::parse(version));
+        assert_eq!(compatible("1.1.0"), Some(true));
+        assert_eq!(compatible("1.1.2"), Some(true));
+        assert_eq!(compatible("1.2.0"), Some(false));
+        assert_eq!(compatible("1.0.9"), Some(false));
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use crate::Mod;
+use crate::files::normalize_path;
+use crate::graph::DependencyGraph;
+use crate::load_order::{by_load_order, load_order};
+use crate::schema::ActionKind;
+use indexmap::IndexMap;
+use serde::Serialize;
+
+/// Actions which put a file at a virtual path, replacing any file already
+/// there.
+pub const OVERRIDING_ACTIONS: &[ActionKind] = &[ActionKind::ImportFiles, ActionKind::UIScripts];
+
+/// A mod providing the file of a [`FileConflict`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileOverride {
+    pub mod_id: String,
+    pub action_group: String,
+    /// The action element, e.g. `ImportFiles`.
+    pub action: String,
+    /// The path as written in the modinfo.
+    pub path: String,
+    pub load_order: i64,
+}
+
+/// Several mods providing the same virtual path in the same scope.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileConflict {
+    pub scope: String,
+    /// Lowercase, with `/` separators, as the game resolves it on Windows.
+    pub path: String,
+    /// In load order: the last one is the file the game ends up with.
+    pub overrides: Vec<FileOverride>,
+    pub winner: String,
+    /// Whether the winner shares its `LoadOrder` with another mod that neither
+    /// requires it nor is required by it, so the game may pick that one
+    /// instead. Same rule as [`LoadPlan::ties`](crate::LoadPlan::ties).
+    pub ambiguous: bool,
+}
+
+/// Finds virtual paths imported by more than one of `mods`, whatever the
+/// criteria of their action groups, as they can clash in some age or setup.
+pub fn detect_file_conflicts<'a>(mods: impl IntoIterator<Item = &'a Mod>) -> Vec<FileConflict> {
+    let mods: Vec<&Mod> = mods.into_iter().collect();
+    let graph = DependencyGraph::new(mods.iter().copied());
+    let mut by_path: IndexMap<(String, String), Vec<FileOverride>> = IndexMap::new();
+    for (modinfo, (), group) in by_load_order(mods.iter().map(|modinfo| (*modinfo, ()))) {
+        let scope = group.scope.clone().unwrap_or_default();
+        for actions in &group.actions {
+            for kind in OVERRIDING_ACTIONS {
+                for item in actions.items(*kind) {
+                    let path = normalize_path(&item.path).to_lowercase();
+                    if path.is_empty() {
+                        continue;
+                    }
+                    by_path
+                        .entry((scope.clone(), path))
+                        .or_default()
+                        .push(FileOverride {
+                            mod_id: modinfo.id.clone(),
+                            action_group: group.id.clone(),
+                            action: kind.name().to_string(),
+                            path: item.path.clone(),
+                            load_order: load_order(group),
+                        });
+                }
+            }
+        }
+    }
+
+    by_path
+        .into_iter()
+        .filter_map(|((scope, path), overrides)| {
+            let winner = overrides.last()?;
+            if overrides.iter().all(|o| o.mod_id == winner.mod_id) {
+                return None;
+            }
+            let ambiguous = overrides.iter().any(|o| {
+                o.mod_id != winner.mod_id
+                    && o.load_order == winner.load_order
+                    && !graph.is_ordered(&o.mod_id, &winner.mod_id)
+            });
+            Some(FileConflict {
+                scope,
+                path,
+                winner: winner.mod_id.clone(),
+                ambiguous,
+                overrides,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::criteria::GameContext;
+    use crate::load_order::simulate_load_order;
+    use crate::schema::ActionGroup;
+
+    #[test]
+    fn test_detect_file_conflicts() -> anyhow::Result<()> {
+        let ui = Mod::builder("ui-mod")
+            .action_group(
+                ActionGroup::builder("shell", "shell")
+                    .load_order(5)
+                    .action(ActionKind::ImportFiles, "ui/shell/main-menu.js")
+                    .action(ActionKind::ImportFiles, "ui/shell/unique.js"),
+            )
+            .action_group(
+                ActionGroup::builder("game", "game")
+                    .action(ActionKind::UIScripts, "ui/city-banner.js"),
+            )
+            .build()?;
+        let other = Mod::builder("other-ui")
+            .action_group(
+                ActionGroup::builder("shell", "shell")
+                    .load_order(1)
+                    .action(ActionKind::ImportFiles, "UI\\Shell\\Main-Menu.js"),
+            )
+            .action_group(
+                ActionGroup::builder("game", "game")
+                    .action(ActionKind::ImportFiles, "./ui/city-banner.js")
+                    // Same file in another scope
+                    .action(ActionKind::ImportFiles, "ui/shell/unique.js"),
+            )
+            .build()?;
+
+        let conflicts = detect_file_conflicts([&ui, &other]);
+        let summary: Vec<(&str, &str, &str, bool)> = conflicts
+            .iter()
+            .map(|c| {
+                (
+                    c.scope.as_str(),
+                    c.path.as_str(),
+                    c.winner.as_str(),
+                    c.ambiguous,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("game", "ui/city-banner.js", "other-ui", true),
+                ("shell", "ui/shell/main-menu.js", "ui-mod", false),
+            ]
+        );
+        assert_eq!(conflicts[1].overrides[0].path, "UI\\Shell\\Main-Menu.js");
+        assert_eq!(conflicts[0].overrides[0].action, "UIScripts");
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_file_conflicts_dependency_order() -> anyhow::Result<()> {
+        let import = |id: &str, dependencies: &[&str]| {
+            dependencies
+                .iter()
+                .fold(Mod::builder(id), |builder, dependency| {
+                    builder.dependency(*dependency, None)
+                })
+                .action_group(
+                    ActionGroup::builder("game", "game").action(ActionKind::ImportFiles, "ui/x.js"),
+                )
+                .build()
+        };
+        let a = import("a", &[])?;
+        let b = import("b", &["a"])?;
+
+        // `b` loads after `a`, which it requires
+        let conflicts = detect_file_conflicts([&b, &a]);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].winner, "b");
+        assert!(!conflicts[0].ambiguous);
+        let plan = simulate_load_order([&b, &a], "game", &GameContext::default());
+        assert_eq!(plan.files.len(), 2);
+        assert_eq!(plan.ties, []);
+
+        let c = import("c", &[])?;
+        let conflicts = detect_file_conflicts([&b, &a, &c]);
+        assert!(conflicts[0].ambiguous);
+        Ok(())
+    }
+}
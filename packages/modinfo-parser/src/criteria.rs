@@ -0,0 +1,365 @@
+use crate::Mod;
+use crate::schema::{
+    ActionCriterion, ActionGroup, ConditionValue, ConfigurationCondition, CriterionCondition,
+};
+use std::collections::{HashMap, HashSet};
+
+/// A simulated game setup to evaluate `<Criteria>` against, without launching
+/// the game.
+///
+/// Whatever the context leaves unset is unknown: conditions on it evaluate to
+/// `None` rather than guessing.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GameContext {
+    /// The current age, e.g. `AGE_EXPLORATION`, or `None` in the shell.
+    pub age: Option<String>,
+    /// Ages played before the current one, for `AgeWasUsed`.
+    pub previous_ages: Vec<String>,
+    pub active_mods: HashSet<String>,
+    pub rule_set: Option<String>,
+    /// Setup parameters by `(Group, ConfigurationId)`.
+    pub configuration: HashMap<(String, String), String>,
+}
+
+impl GameContext {
+    /// A game in `age`, e.g. `AGE_ANTIQUITY`.
+    pub fn in_age(age: impl Into<String>) -> Self {
+        Self {
+            age: Some(age.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_previous_ages(mut self, ages: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.previous_ages.extend(ages.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_active_mods(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.active_mods.extend(ids.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_rule_set(mut self, rule_set: impl Into<String>) -> Self {
+        self.rule_set = Some(rule_set.into());
+        self
+    }
+
+    pub fn with_configuration(
+        mut self,
+        group: impl Into<String>,
+        configuration_id: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.configuration
+            .insert((group.into(), configuration_id.into()), value.into());
+        self
+    }
+
+    fn configuration_value(&self, condition: &ConfigurationCondition) -> Option<&str> {
+        self.configuration
+            .get(&(condition.group.clone(), condition.configuration_id.clone()))
+            .map(String::as_str)
+    }
+}
+
+/// Applies `inverse` to a known result.
+fn invert(met: Option<bool>, inverse: bool) -> Option<bool> {
+    met.map(|met| met != inverse)
+}
+
+impl CriterionCondition {
+    /// Whether the condition is met in `context`, or `None` when the context
+    /// doesn't tell, e.g. for `LeaderPlayable` or unknown conditions.
+    pub fn evaluate(&self, context: &GameContext) -> Option<bool> {
+        let value = |condition: &ConditionValue, met: Option<bool>| invert(met, condition.inverse);
+        match self {
+            CriterionCondition::AlwaysMet => Some(true),
+            CriterionCondition::NeverMet => Some(false),
+            CriterionCondition::AgeInUse(age) => value(
+                age,
+                context.age.as_ref().map(|current| *current == age.value),
+            ),
+            CriterionCondition::AgeWasUsed(age) => value(
+                age,
+                context
+                    .age
+                    .as_ref()
+                    .map(|_| context.previous_ages.contains(&age.value)),
+            ),
+            CriterionCondition::AgeEverInUse(age) => value(
+                age,
+                context.age.as_ref().map(|current| {
+                    *current == age.value || context.previous_ages.contains(&age.value)
+                }),
+            ),
+            CriterionCondition::ModInUse(id) => {
+                value(id, Some(context.active_mods.contains(&id.value)))
+            }
+            CriterionCondition::RuleSetInUse(rule_set) => value(
+                rule_set,
+                context
+                    .rule_set
+                    .as_ref()
+                    .map(|current| *current == rule_set.value),
+            ),
+            CriterionCondition::LeaderPlayable(_)
+            | CriterionCondition::CivilizationPlayable(_)
+            | CriterionCondition::Unknown { .. } => None,
+            CriterionCondition::ConfigurationValueMatches(condition) => invert(
+                context
+                    .configuration_value(condition)
+                    .map(|actual| actual == condition.value),
+                condition.inverse,
+            ),
+            CriterionCondition::ConfigurationValueContains(condition) => invert(
+                context
+                    .configuration_value(condition)
+                    .map(|actual| actual.split(',').any(|item| item.trim() == condition.value)),
+                condition.inverse,
+            ),
+        }
+    }
+}
+
+impl ActionCriterion {
+    /// Combines the conditions with three-valued logic: an unknown condition
+    /// only makes the result unknown when the known ones don't settle it.
+    pub fn evaluate(&self, context: &GameContext) -> Option<bool> {
+        let results = self
+            .conditions
+            .iter()
+            .map(|condition| condition.evaluate(context));
+        let (settled, unsettled) = if self.any {
+            (true, false)
+        } else {
+            (false, true)
+        };
+        let mut unknown = false;
+        for result in results {
+            match result {
+                Some(met) if met == settled => return Some(settled),
+                Some(_) => {}
+                None => unknown = true,
+            }
+        }
+        if unknown { None } else { Some(unsettled) }
+    }
+}
+
+impl Mod {
+    /// Evaluates every `<Criteria>` of the mod, in order.
+    pub fn evaluate_criteria<'a>(
+        &'a self,
+        context: &GameContext,
+    ) -> Vec<(&'a ActionCriterion, Option<bool>)> {
+        self.action_criteria
+            .iter()
+            .map(|criterion| (criterion, criterion.evaluate(context)))
+            .collect()
+    }
+
+    /// Whether each action group would be active in `context`. Groups without
+    /// criteria always are, and groups whose criteria is not defined are
+    /// unknown.
+    pub fn evaluate_action_groups<'a>(
+        &'a self,
+        context: &GameContext,
+    ) -> Vec<(&'a ActionGroup, Option<bool>)> {
+        self.action_groups
+            .iter()
+            .map(|group| (group, self.evaluate_action_group(group, context)))
+            .collect()
+    }
+
+    /// Whether `group`, one of the mod's action groups, would be active.
+    pub fn evaluate_action_group(
+        &self,
+        group: &ActionGroup,
+        context: &GameContext,
+    ) -> Option<bool> {
+        match &group.criteria {
+            None => Some(true),
+            Some(id) => self
+                .action_criteria
+                .iter()
+                .find(|criterion| criterion.id == *id)
+                .and_then(|criterion| criterion.evaluate(context)),
+        }
+    }
+
+    /// Action groups that are certainly active in `context`.
+    pub fn active_action_groups<'a>(&'a self, context: &GameContext) -> Vec<&'a ActionGroup> {
+        self.evaluate_action_groups(context)
+            .into_iter()
+            .filter(|(_, active)| *active == Some(true))
+            .map(|(group, _)| group)
+            .collect()
+    }
+}
+
+/// Active action groups of each mod, e.g. to tell which of the installed mods
+/// do anything in a given age. Mods without any are left out.
+pub fn active_action_groups<'a>(
+    mods: impl IntoIterator<Item = &'a Mod>,
+    context: &GameContext,
+) -> Vec<(&'a Mod, Vec<&'a ActionGroup>)> {
+    mods.into_iter()
+        .map(|modinfo| (modinfo, modinfo.active_action_groups(context)))
+        .filter(|(_, groups)| !groups.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_evaluate_conditions() {
+        let context = GameContext::in_age("AGE_EXPLORATION")
+            .with_previous_ages(["AGE_ANTIQUITY"])
+            .with_active_mods(["other-mod"])
+            .with_configuration("Game", "Difficulty", "Deity")
+            .with_configuration("Map", "Options", "A, B");
+        let inverse = |value: &str| ConditionValue {
+            value: value.to_string(),
+            inverse: true,
+        };
+        let configuration = |group: &str, id: &str, value: &str| ConfigurationCondition {
+            inverse: false,
+            group: group.to_string(),
+            configuration_id: id.to_string(),
+            value: value.to_string(),
+        };
+
+        let cases = [
+            (
+                CriterionCondition::AgeInUse("AGE_EXPLORATION".into()),
+                Some(true),
+            ),
+            (
+                CriterionCondition::AgeInUse("AGE_MODERN".into()),
+                Some(false),
+            ),
+            (
+                CriterionCondition::AgeWasUsed("AGE_ANTIQUITY".into()),
+                Some(true),
+            ),
+            (
+                CriterionCondition::AgeWasUsed("AGE_EXPLORATION".into()),
+                Some(false),
+            ),
+            (
+                CriterionCondition::AgeEverInUse("AGE_EXPLORATION".into()),
+                Some(true),
+            ),
+            (CriterionCondition::ModInUse("other-mod".into()), Some(true)),
+            (
+                CriterionCondition::ModInUse(inverse("other-mod")),
+                Some(false),
+            ),
+            (
+                CriterionCondition::ModInUse(inverse("missing-mod")),
+                Some(true),
+            ),
+            (
+                CriterionCondition::RuleSetInUse("RULESET_STANDARD".into()),
+                None,
+            ),
+            (CriterionCondition::LeaderPlayable("LEADER_X".into()), None),
+            (
+                CriterionCondition::ConfigurationValueMatches(configuration(
+                    "Game",
+                    "Difficulty",
+                    "Deity",
+                )),
+                Some(true),
+            ),
+            (
+                CriterionCondition::ConfigurationValueContains(configuration(
+                    "Map", "Options", "B",
+                )),
+                Some(true),
+            ),
+            (
+                CriterionCondition::ConfigurationValueMatches(configuration(
+                    "Game", "Speed", "Quick",
+                )),
+                None,
+            ),
+        ];
+        for (condition, expected) in cases {
+            assert_eq!(condition.evaluate(&context), expected, "{condition:?}");
+        }
+    }
+
+    #[test]
+    fn test_active_action_groups() -> anyhow::Result<()> {
+        let modinfo = Mod::parse(
+            indoc! {r#"
+                <Mod id="a_mod">
+                    <ActionCriteria>
+                        <Criteria id="exploration">
+                            <AgeInUse>AGE_EXPLORATION</AgeInUse>
+                        </Criteria>
+                        <Criteria id="not-antiquity-or-leader" any="true">
+                            <AgeInUse inverse="1">AGE_ANTIQUITY</AgeInUse>
+                            <LeaderPlayable>LEADER_X</LeaderPlayable>
+                        </Criteria>
+                        <Criteria id="exploration-and-leader">
+                            <AgeInUse>AGE_EXPLORATION</AgeInUse>
+                            <LeaderPlayable>LEADER_X</LeaderPlayable>
+                        </Criteria>
+                    </ActionCriteria>
+                    <ActionGroups>
+                        <ActionGroup id="always" scope="game" />
+                        <ActionGroup id="exploration" scope="game" criteria="exploration" />
+                        <ActionGroup id="either" scope="game" criteria="not-antiquity-or-leader" />
+                        <ActionGroup id="both" scope="game" criteria="exploration-and-leader" />
+                    </ActionGroups>
+                </Mod>
+            "#}
+            .as_bytes(),
+        )?;
+
+        let ids = |context: &GameContext| -> Vec<(String, Option<bool>)> {
+            modinfo
+                .evaluate_action_groups(context)
+                .into_iter()
+                .map(|(group, active)| (group.id.clone(), active))
+                .collect()
+        };
+        let expected = |states: [Option<bool>; 4]| -> Vec<(String, Option<bool>)> {
+            ["always", "exploration", "either", "both"]
+                .into_iter()
+                .map(str::to_string)
+                .zip(states)
+                .collect()
+        };
+
+        let exploration = GameContext::in_age("AGE_EXPLORATION");
+        assert_eq!(
+            ids(&exploration),
+            expected([Some(true), Some(true), Some(true), None])
+        );
+        let antiquity = GameContext::in_age("AGE_ANTIQUITY");
+        assert_eq!(
+            ids(&antiquity),
+            expected([Some(true), Some(false), None, Some(false)])
+        );
+
+        let active: Vec<&str> = modinfo
+            .active_action_groups(&exploration)
+            .iter()
+            .map(|group| group.id.as_str())
+            .collect();
+        assert_eq!(active, ["always", "exploration", "either"]);
+
+        let other = Mod::builder("other-mod").build()?;
+        let by_mod = active_action_groups([&modinfo, &other], &exploration);
+        assert_eq!(by_mod.len(), 1);
+        assert_eq!(by_mod[0].0.id, "a_mod");
+        Ok(())
+    }
+}
@@ -0,0 +1,927 @@
+use crate::Mod;
+use crate::files::read_mod_file;
+use crate::load_order::{by_load_order, load_order};
+use crate::schema::ActionKind;
+use indexmap::IndexMap;
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum DatabaseOperation {
+    /// `INSERT`, `REPLACE`, `<Row>` or `<Replace>`.
+    Insert,
+    Update,
+    Delete,
+}
+
+/// A change made by an `UpdateDatabase` file to a table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DatabaseChange {
+    pub table: String,
+    /// The primary key of the row, e.g. `UNIT_WARRIOR`, or `None` when the
+    /// change may touch any row, e.g. an `UPDATE` without a simple `WHERE`
+    /// or with only part of a composite key.
+    /// Composite keys are joined in column order, e.g. `UNIT_WARRIOR, ABILITY_X`.
+    pub key: Option<String>,
+    pub operation: DatabaseOperation,
+}
+
+/// Primary keys of the base game tables that mods commonly change. A column
+/// named like a key, e.g. `ModifierType` or `TraitType` in `Units`, is often
+/// just a reference to another table.
+const PRIMARY_KEYS: &[(&str, &[&str])] = &[
+    ("Types", &["Type"]),
+    ("Kinds", &["Kind"]),
+    ("Tags", &["Tag"]),
+    ("TypeTags", &["Type", "Tag"]),
+    ("Units", &["UnitType"]),
+    ("UnitAbilities", &["UnitAbilityType"]),
+    ("Unit_Abilities", &["UnitType", "UnitAbilityType"]),
+    ("Unit_Stats", &["UnitType"]),
+    ("Unit_Movements", &["UnitType"]),
+    ("Modifiers", &["ModifierId"]),
+    ("ModifierArguments", &["ModifierId", "Name"]),
+    ("ModifierStrings", &["ModifierId", "Context"]),
+    ("DynamicModifiers", &["ModifierType"]),
+    ("Requirements", &["RequirementId"]),
+    ("RequirementArguments", &["RequirementId", "Name"]),
+    ("RequirementSets", &["RequirementSetId"]),
+    (
+        "RequirementSetRequirements",
+        &["RequirementSetId", "RequirementId"],
+    ),
+    ("Traits", &["TraitType"]),
+    ("TraitModifiers", &["TraitType", "ModifierId"]),
+    ("Civilizations", &["CivilizationType"]),
+    ("CivilizationTraits", &["CivilizationType", "TraitType"]),
+    ("Leaders", &["LeaderType"]),
+    ("LeaderTraits", &["LeaderType", "TraitType"]),
+    ("Constructibles", &["ConstructibleType"]),
+    ("Buildings", &["ConstructibleType"]),
+    ("Improvements", &["ConstructibleType"]),
+    ("Wonders", &["ConstructibleType"]),
+    (
+        "Constructible_YieldChanges",
+        &["ConstructibleType", "YieldType"],
+    ),
+    ("Resources", &["ResourceType"]),
+    ("Terrains", &["TerrainType"]),
+    ("Features", &["FeatureType"]),
+    ("Yields", &["YieldType"]),
+    ("Traditions", &["TraditionType"]),
+    ("Beliefs", &["BeliefType"]),
+    ("ProgressionTrees", &["ProgressionTreeType"]),
+    ("ProgressionTreeNodes", &["ProgressionTreeNodeType"]),
+    ("GlobalParameters", &["Name"]),
+];
+
+fn primary_key(table: &str) -> Option<&'static [&'static str]> {
+    PRIMARY_KEYS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(table))
+        .map(|(_, keys)| *keys)
+}
+
+/// Key columns of `table`, from [`PRIMARY_KEYS`]. Other tables fall back to
+/// the first of `columns`, which usually comes first in the table too.
+fn key_columns<'c>(table: &str, columns: impl IntoIterator<Item = &'c str>) -> Vec<&'c str> {
+    match primary_key(table) {
+        Some(keys) => keys.to_vec(),
+        None => columns.into_iter().take(1).collect(),
+    }
+}
+
+/// Position of `column` in `columns`, which SQL compares without case.
+fn column_position<'c>(mut columns: impl Iterator<Item = &'c str>, column: &str) -> Option<usize> {
+    columns.position(|name| name.eq_ignore_ascii_case(column))
+}
+
+/// Joins the values of the key columns, `None` if one of them is unknown.
+fn composite_key(values: impl IntoIterator<Item = Option<String>>) -> Option<String> {
+    let values: Option<Vec<String>> = values.into_iter().collect();
+    values
+        .filter(|values| !values.is_empty())
+        .map(|values| values.join(", "))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// A keyword or identifier, unquoted.
+    Word(String),
+    /// A string or number.
+    Literal(String),
+    Symbol(char),
+}
+
+fn tokenize(sql: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '-' if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '\'' | '"' | '`' | '[' => {
+                let close = if c == '[' { ']' } else { c };
+                let mut value = String::new();
+                while let Some(c) = chars.next() {
+                    if c == close {
+                        // A doubled quote is an escaped quote
+                        if close != ']' && chars.peek() == Some(&close) {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    value.push(c);
+                }
+                tokens.push(if c == '\'' {
+                    Token::Literal(value)
+                } else {
+                    Token::Word(value)
+                });
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' => {
+                let mut value = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '.') {
+                        break;
+                    }
+                    value.push(c);
+                    chars.next();
+                }
+                tokens.push(if c.is_ascii_digit() || c == '.' {
+                    Token::Literal(value)
+                } else {
+                    Token::Word(value)
+                });
+            }
+            c => tokens.push(Token::Symbol(c)),
+        }
+    }
+    tokens
+}
+
+/// Cursor over the tokens of one statement.
+struct Statement<'t> {
+    tokens: &'t [Token],
+    position: usize,
+}
+
+impl<'t> Statement<'t> {
+    fn peek(&self) -> Option<&'t Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'t Token> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn is_keyword(token: Option<&Token>, keyword: &str) -> bool {
+        matches!(token, Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    /// Skips tokens up to and including `keyword`.
+    fn skip_past(&mut self, keyword: &str) -> bool {
+        while let Some(token) = self.next() {
+            if Self::is_keyword(Some(token), keyword) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn word(&mut self) -> Option<String> {
+        match self.next() {
+            Some(Token::Word(word)) => Some(word.clone()),
+            _ => None,
+        }
+    }
+
+    /// Reads a parenthesized, comma separated list, keeping the first token
+    /// of every element. Nested parentheses are skipped.
+    fn list(&mut self) -> Option<Vec<Option<&'t Token>>> {
+        if self.next() != Some(&Token::Symbol('(')) {
+            return None;
+        }
+        let mut elements = vec![];
+        let mut current = None;
+        let mut first = true;
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token {
+                Token::Symbol('(') => depth += 1,
+                Token::Symbol(')') if depth > 0 => depth -= 1,
+                Token::Symbol(')') => {
+                    elements.push(current);
+                    return Some(elements);
+                }
+                Token::Symbol(',') if depth == 0 => {
+                    elements.push(current.take());
+                    first = true;
+                    continue;
+                }
+                _ => {}
+            }
+            if first {
+                current = Some(token);
+                first = false;
+            }
+        }
+        None
+    }
+
+    /// Values of `column = 'value'` and `column IN ('a', 'b')` in the rest
+    /// of the statement, by column.
+    fn conditions(&mut self) -> IndexMap<String, Vec<String>> {
+        let mut conditions: IndexMap<String, Vec<String>> = IndexMap::new();
+        while let Some(token) = self.next() {
+            let Token::Word(column) = token else {
+                continue;
+            };
+            match self.peek() {
+                Some(Token::Symbol('=')) => {
+                    self.next();
+                    if let Some(Token::Literal(value)) = self.peek() {
+                        self.next();
+                        conditions
+                            .entry(column.clone())
+                            .or_default()
+                            .push(value.clone());
+                    }
+                }
+                token if Self::is_keyword(token, "IN") => {
+                    self.next();
+                    if let Some(values) = self.list() {
+                        let values = values.into_iter().filter_map(|value| match value {
+                            Some(Token::Literal(value)) => Some(value.clone()),
+                            _ => None,
+                        });
+                        conditions.entry(column.clone()).or_default().extend(values);
+                    }
+                }
+                _ => {}
+            }
+        }
+        conditions
+    }
+}
+
+/// Changes on `table`, one per value of the key columns of `conditions`, or
+/// a single table-wide one when they don't pin down the whole key.
+fn keyed_changes(
+    table: String,
+    operation: DatabaseOperation,
+    conditions: IndexMap<String, Vec<String>>,
+) -> Vec<DatabaseChange> {
+    // Every combination of the values of the key columns, for `IN (...)`
+    let mut keys: Vec<Vec<String>> = vec![vec![]];
+    for column in key_columns(&table, conditions.keys().map(String::as_str)) {
+        let Some(index) = column_position(conditions.keys().map(String::as_str), column) else {
+            keys.clear();
+            break;
+        };
+        let values = &conditions[index];
+        keys = keys
+            .into_iter()
+            .flat_map(|key| {
+                values.iter().map(move |value| {
+                    let mut key = key.clone();
+                    key.push(value.clone());
+                    key
+                })
+            })
+            .collect();
+    }
+    let keys: Vec<String> = keys
+        .into_iter()
+        .filter_map(|key| composite_key(key.into_iter().map(Some)))
+        .collect();
+    if keys.is_empty() {
+        return vec![DatabaseChange {
+            table,
+            key: None,
+            operation,
+        }];
+    }
+    keys.into_iter()
+        .map(|key| DatabaseChange {
+            table: table.clone(),
+            key: Some(key),
+            operation,
+        })
+        .collect()
+}
+
+fn parse_statement(mut statement: Statement) -> Vec<DatabaseChange> {
+    let first = statement.peek();
+    if Statement::is_keyword(first, "INSERT") || Statement::is_keyword(first, "REPLACE") {
+        if !statement.skip_past("INTO") {
+            return vec![];
+        }
+        let Some(table) = statement.word() else {
+            return vec![];
+        };
+        let columns: Vec<String> = if statement.peek() == Some(&Token::Symbol('(')) {
+            let columns = statement.list().unwrap_or_default();
+            columns
+                .into_iter()
+                .map(|column| match column {
+                    Some(Token::Word(column)) => column.clone(),
+                    _ => String::new(),
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        // Without a column list, values are in table order: the key usually comes first
+        let key_indices: Vec<Option<usize>> = if columns.is_empty() {
+            let length = primary_key(&table).map_or(1, <[_]>::len);
+            (0..length).map(Some).collect()
+        } else {
+            key_columns(&table, columns.iter().map(String::as_str))
+                .into_iter()
+                .map(|key| column_position(columns.iter().map(String::as_str), key))
+                .collect()
+        };
+
+        let insert = |key: Option<String>| DatabaseChange {
+            table: table.clone(),
+            key,
+            operation: DatabaseOperation::Insert,
+        };
+        if !Statement::is_keyword(statement.next(), "VALUES") {
+            // INSERT ... SELECT
+            return vec![insert(None)];
+        }
+        let mut changes = vec![];
+        while let Some(row) = statement.list() {
+            let key = composite_key(key_indices.iter().map(|index| {
+                match index.and_then(|index| row.get(index)) {
+                    Some(Some(Token::Literal(value))) => Some(value.clone()),
+                    _ => None,
+                }
+            }));
+            changes.push(insert(key));
+            if statement.peek() != Some(&Token::Symbol(',')) {
+                break;
+            }
+            statement.next();
+        }
+        changes
+    } else if Statement::is_keyword(first, "UPDATE") {
+        statement.next();
+        // UPDATE OR REPLACE
+        if Statement::is_keyword(statement.peek(), "OR") {
+            statement.next();
+            statement.next();
+        }
+        let Some(table) = statement.word() else {
+            return vec![];
+        };
+        let conditions = if statement.skip_past("WHERE") {
+            statement.conditions()
+        } else {
+            IndexMap::new()
+        };
+        keyed_changes(table, DatabaseOperation::Update, conditions)
+    } else if Statement::is_keyword(first, "DELETE") {
+        if !statement.skip_past("FROM") {
+            return vec![];
+        }
+        let Some(table) = statement.word() else {
+            return vec![];
+        };
+        let conditions = if statement.skip_past("WHERE") {
+            statement.conditions()
+        } else {
+            IndexMap::new()
+        };
+        keyed_changes(table, DatabaseOperation::Delete, conditions)
+    } else {
+        vec![]
+    }
+}
+
+/// Extracts the rows inserted, updated or deleted by an SQL file. This is not
+/// a full SQL parser: statements it doesn't understand are ignored.
+pub fn parse_sql(sql: &str) -> Vec<DatabaseChange> {
+    let tokens = tokenize(sql);
+    tokens
+        .split(|token| *token == Token::Symbol(';'))
+        .flat_map(|tokens| {
+            parse_statement(Statement {
+                tokens,
+                position: 0,
+            })
+        })
+        .collect()
+}
+
+fn attributes(tag: &BytesStart) -> Result<Vec<(String, String)>, quick_xml::Error> {
+    tag.attributes()
+        .map(|attribute| {
+            let attribute = attribute?;
+            let name = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+            Ok((name, attribute.unescape_value()?.to_string()))
+        })
+        .collect()
+}
+
+/// A child of a table in a `<Database>` XML file, e.g. `<Row>` or `<Update>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DatabaseRow {
+    pub table: String,
+    /// `Row`, `Replace`, `Update`, ...
+    pub element: String,
+    /// Its attributes, then the text of its child elements, e.g.
+    /// `<Row Tag="LOC_X"><Text>X</Text></Row>`.
+    pub columns: Vec<(String, String)>,
+    /// The attributes of its child elements, e.g. `<Where UnitType="UNIT_X"/>`.
+    pub children: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl DatabaseRow {
+    pub fn get(&self, column: &str) -> Option<&str> {
+        self.columns
+            .iter()
+            .find(|(name, _)| name == column)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reads the rows of a database XML file, as used by `UpdateDatabase` and
+/// `UpdateText` files:
+///
+/// ```xml
+/// <Database>
+///     <Units>
+///         <Row UnitType="UNIT_X"><Cost>10</Cost></Row>
+///     </Units>
+/// </Database>
+/// ```
+pub(crate) fn read_database_rows(xml: &str) -> Result<Vec<DatabaseRow>, quick_xml::Error> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut rows = vec![];
+    let mut depth = 0;
+    let mut table = String::new();
+    let mut row: Option<DatabaseRow> = None;
+    let mut column: Option<String> = None;
+
+    loop {
+        let event = reader.read_event()?;
+        let (tag, empty) = match &event {
+            Event::Start(tag) => (Some(tag), false),
+            Event::Empty(tag) => (Some(tag), true),
+            _ => (None, false),
+        };
+        if let Some(tag) = tag {
+            let name = String::from_utf8_lossy(tag.local_name().as_ref()).to_string();
+            match depth {
+                1 => table = name,
+                2 => {
+                    row = Some(DatabaseRow {
+                        table: table.clone(),
+                        element: name,
+                        columns: attributes(tag)?,
+                        children: vec![],
+                    });
+                    if empty {
+                        rows.extend(row.take());
+                    }
+                }
+                3 => {
+                    if let Some(row) = &mut row {
+                        row.children.push((name.clone(), attributes(tag)?));
+                        if empty {
+                            row.columns.push((name, String::new()));
+                        } else {
+                            column = Some(name);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            if !empty {
+                depth += 1;
+            }
+            continue;
+        }
+        match event {
+            Event::End(_) => {
+                depth -= 1;
+                match depth {
+                    2 => rows.extend(row.take()),
+                    3 => column = None,
+                    _ => {}
+                }
+            }
+            Event::Text(text) => {
+                if let (Some(name), Some(row)) = (column.take(), &mut row) {
+                    row.columns.push((name, text.unescape()?.to_string()));
+                }
+            }
+            Event::CData(text) => {
+                if let (Some(name), Some(row)) = (column.take(), &mut row) {
+                    row.columns
+                        .push((name, String::from_utf8_lossy(&text).to_string()));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(rows)
+}
+
+/// Extracts the rows changed by a database XML file, e.g.
+/// `<Database><Units><Row UnitType="UNIT_X"/></Units></Database>`.
+pub fn parse_database_xml(xml: &str) -> Result<Vec<DatabaseChange>, quick_xml::Error> {
+    let rows = read_database_rows(xml)?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let (operation, columns) = match row.element.as_str() {
+                "Row" | "Replace" | "InsertOrIgnore" => (DatabaseOperation::Insert, row.columns),
+                "Update" => {
+                    let conditions = row.children.into_iter().filter(|(name, _)| name == "Where");
+                    let columns = conditions.flat_map(|(_, attributes)| attributes);
+                    (DatabaseOperation::Update, columns.collect())
+                }
+                "Delete" => (DatabaseOperation::Delete, row.columns),
+                _ => return None,
+            };
+            let names = || columns.iter().map(|(name, _)| name.as_str());
+            let key = composite_key(key_columns(&row.table, names()).into_iter().map(|key| {
+                let index = column_position(names(), key)?;
+                Some(columns[index].1.clone())
+            }));
+            Some(DatabaseChange {
+                table: row.table,
+                key,
+                operation,
+            })
+        })
+        .collect())
+}
+
+/// Reads the changes of an `UpdateDatabase` file, by extension. Unreadable
+/// files are logged and skipped, as the game skips them too.
+fn read_changes(mod_folder: &Path, path: &str) -> Vec<DatabaseChange> {
+    let Some((file_path, text)) = read_mod_file(mod_folder, path) else {
+        return vec![];
+    };
+    let extension = file_path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("sql") => parse_sql(&text),
+        Some("xml") => parse_database_xml(&text).unwrap_or_else(|err| {
+            log::warn!("Invalid XML in {}: {err}", file_path.display());
+            vec![]
+        }),
+        _ => vec![],
+    }
+}
+
+/// A file touching the row of a [`DatabaseConflict`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DatabaseTouch {
+    pub mod_id: String,
+    pub action_group: String,
+    pub path: String,
+    pub load_order: i64,
+    pub operation: DatabaseOperation,
+    /// The file changes the whole table rather than this row.
+    pub table_wide: bool,
+}
+
+/// Several mods changing the same row, or the same table when `key` is
+/// `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DatabaseConflict {
+    /// `game` or `shell`, which use separate databases.
+    pub scope: String,
+    pub table: String,
+    pub key: Option<String>,
+    /// In load order: later changes override earlier ones.
+    pub touches: Vec<DatabaseTouch>,
+}
+
+/// Parses the `UpdateDatabase` files of `mods`, given with the folder of their
+/// modinfo, and reports the rows changed by more than one mod.
+///
+/// Table-wide updates and deletes overlap with every row of their table.
+/// Table-wide inserts, e.g. `INSERT ... SELECT`, add rows this crate cannot
+/// know, so they only overlap with each other.
+pub fn detect_database_conflicts<'a>(
+    mods: impl IntoIterator<Item = (&'a Mod, &'a Path)>,
+) -> Vec<DatabaseConflict> {
+    type Row = (String, String, Option<String>);
+    // Touches with the position of their file in load order
+    let mut rows: IndexMap<Row, Vec<(usize, DatabaseTouch)>> = IndexMap::new();
+    let mut sequence = 0;
+    for (modinfo, folder, group) in by_load_order(mods) {
+        let scope = group.scope.clone().unwrap_or_default();
+        for actions in &group.actions {
+            for item in actions.items(ActionKind::UpdateDatabase) {
+                sequence += 1;
+                for change in read_changes(folder, &item.path) {
+                    let row = (scope.clone(), change.table, change.key.clone());
+                    let touch = DatabaseTouch {
+                        mod_id: modinfo.id.clone(),
+                        action_group: group.id.clone(),
+                        path: item.path.clone(),
+                        load_order: load_order(group),
+                        operation: change.operation,
+                        table_wide: change.key.is_none(),
+                    };
+                    rows.entry(row).or_default().push((sequence, touch));
+                }
+            }
+        }
+    }
+
+    // Table-wide updates and deletes apply to every row of the table
+    let table_wide: Vec<(Row, Vec<(usize, DatabaseTouch)>)> = rows
+        .iter()
+        .filter(|((_, _, key), _)| key.is_none())
+        .map(|(row, touches)| {
+            let touches = touches
+                .iter()
+                .filter(|(_, touch)| touch.operation != DatabaseOperation::Insert);
+            (row.clone(), touches.cloned().collect())
+        })
+        .collect();
+    for ((scope, table, _), wide) in &table_wide {
+        for ((row_scope, row_table, key), touches) in rows.iter_mut() {
+            if key.is_some() && row_scope == scope && row_table == table {
+                touches.extend(wide.iter().cloned());
+                touches.sort_by_key(|(sequence, _)| *sequence);
+            }
+        }
+    }
+
+    rows.into_iter()
+        .map(|(row, touches)| (row, touches.into_iter().map(|(_, touch)| touch)))
+        .map(|((scope, table, key), touches)| DatabaseConflict {
+            scope,
+            table,
+            key,
+            touches: touches.collect(),
+        })
+        .filter(|conflict| {
+            let touches = &conflict.touches;
+            touches
+                .iter()
+                .any(|touch| touch.mod_id != touches[0].mod_id)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ActionGroup;
+    use indoc::indoc;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn change(table: &str, key: Option<&str>, operation: DatabaseOperation) -> DatabaseChange {
+        DatabaseChange {
+            table: table.to_string(),
+            key: key.map(str::to_string),
+            operation,
+        }
+    }
+
+    #[test]
+    fn test_parse_sql() {
+        let changes = parse_sql(indoc! {r#"
+            -- Stronger warriors; and cheaper
+            INSERT OR REPLACE INTO Types (Type, Kind) VALUES
+                ('UNIT_X', 'KIND_UNIT'), ('UNIT_Y', 'KIND_UNIT');
+            INSERT INTO "Units" ("Name", UnitType, Cost) VALUES ('It''s; X', 'UNIT_X', (10 + 5));
+            UPDATE Units SET Combat = 20 WHERE UnitType = 'UNIT_WARRIOR';
+            /* Whole table */ UPDATE Units SET Cost = Cost * 2;
+            DELETE FROM Unit_Abilities WHERE UnitType IN ('UNIT_A', 'UNIT_B') AND Hidden = 1;
+            INSERT INTO Modifiers (ModifierId) SELECT ModifierId FROM Other;
+            INSERT INTO Unit_Abilities (UnitType, UnitAbilityType) VALUES ('UNIT_A', 'ABILITY_X');
+            UPDATE Unit_Abilities SET Hidden = 0
+                WHERE UnitAbilityType = 'ABILITY_X' AND UnitType IN ('UNIT_A', 'UNIT_B');
+            INSERT INTO Tags VALUES ('CLASS_X', 'ABILITY_CLASS');
+            CREATE TABLE Custom (Id TEXT PRIMARY KEY);
+        "#});
+        use DatabaseOperation::*;
+        assert_eq!(
+            changes,
+            [
+                change("Types", Some("UNIT_X"), Insert),
+                change("Types", Some("UNIT_Y"), Insert),
+                change("Units", Some("UNIT_X"), Insert),
+                change("Units", Some("UNIT_WARRIOR"), Update),
+                change("Units", None, Update),
+                // Only part of the key: any ability of these units
+                change("Unit_Abilities", None, Delete),
+                change("Modifiers", None, Insert),
+                change("Unit_Abilities", Some("UNIT_A, ABILITY_X"), Insert),
+                change("Unit_Abilities", Some("UNIT_A, ABILITY_X"), Update),
+                change("Unit_Abilities", Some("UNIT_B, ABILITY_X"), Update),
+                change("Tags", Some("CLASS_X"), Insert),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_sql_primary_keys() {
+        let changes = parse_sql(indoc! {r#"
+            INSERT INTO Modifiers (ModifierId, ModifierType) VALUES ('MOD_A', 'MODIFIER_X');
+            INSERT INTO TypeTags (Type, Tag) VALUES ('UNIT_X', 'CLASS_A'), ('UNIT_X', 'CLASS_B');
+            INSERT INTO TypeTags VALUES ('UNIT_Y', 'CLASS_A');
+            INSERT INTO Units (UnitType, Cost, TraitType) VALUES ('UNIT_X', 10, 'TRAIT_Y');
+            UPDATE Units SET Cost = 20 WHERE UnitType = 'UNIT_X';
+            INSERT INTO CustomTable (Id, OtherType) VALUES ('ID_A', 'TYPE_B');
+        "#});
+        use DatabaseOperation::*;
+        assert_eq!(
+            changes,
+            [
+                change("Modifiers", Some("MOD_A"), Insert),
+                change("TypeTags", Some("UNIT_X, CLASS_A"), Insert),
+                change("TypeTags", Some("UNIT_X, CLASS_B"), Insert),
+                change("TypeTags", Some("UNIT_Y, CLASS_A"), Insert),
+                change("Units", Some("UNIT_X"), Insert),
+                change("Units", Some("UNIT_X"), Update),
+                change("CustomTable", Some("ID_A"), Insert),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_database_xml() -> anyhow::Result<()> {
+        let changes = parse_database_xml(indoc! {r#"
+            <?xml version="1.0" encoding="utf-8"?>
+            <Database>
+                <Units>
+                    <Row UnitType="UNIT_X" Cost="10"/>
+                    <Row>
+                        <Name>LOC_UNIT_Y</Name>
+                        <UnitType>UNIT_Y</UnitType>
+                    </Row>
+                    <Update>
+                        <Where UnitType="UNIT_WARRIOR"/>
+                        <Set Combat="20"/>
+                    </Update>
+                    <Delete UnitType="UNIT_SCOUT"/>
+                </Units>
+                <Unit_Abilities>
+                    <Row UnitType="UNIT_X" UnitAbilityType="ABILITY_Y"/>
+                </Unit_Abilities>
+                <Modifiers>
+                    <Delete/>
+                </Modifiers>
+            </Database>
+        "#})?;
+        use DatabaseOperation::*;
+        assert_eq!(
+            changes,
+            [
+                change("Units", Some("UNIT_X"), Insert),
+                change("Units", Some("UNIT_Y"), Insert),
+                change("Units", Some("UNIT_WARRIOR"), Update),
+                change("Units", Some("UNIT_SCOUT"), Delete),
+                change("Unit_Abilities", Some("UNIT_X, ABILITY_Y"), Insert),
+                change("Modifiers", None, Delete),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_database_conflicts() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let a_folder = dir.path().join("a");
+        let b_folder = dir.path().join("b");
+        fs::create_dir_all(a_folder.join("data"))?;
+        fs::create_dir_all(&b_folder)?;
+        fs::write(
+            a_folder.join("data/units.sql"),
+            "UPDATE Units SET Combat = 20 WHERE UnitType = 'UNIT_WARRIOR';\
+             UPDATE Units SET Cost = 1 WHERE UnitType = 'UNIT_SCOUT';\
+             INSERT INTO Unit_Abilities (UnitType, UnitAbilityType) VALUES ('UNIT_WARRIOR', 'A');",
+        )?;
+        fs::write(
+            b_folder.join("units.xml"),
+            r#"<Database><Units><Update><Where UnitType="UNIT_WARRIOR"/><Set Combat="25"/></Update></Units><Civilizations><Delete/></Civilizations></Database>"#,
+        )?;
+        fs::write(
+            b_folder.join("all.sql"),
+            "UPDATE Units SET Cost = Cost * 2;\
+             INSERT INTO Units (UnitType) SELECT UnitType FROM Units;\
+             INSERT INTO Unit_Abilities (UnitType, UnitAbilityType) VALUES ('UNIT_WARRIOR', 'B');",
+        )?;
+
+        let a = Mod::builder("a")
+            .action_group(
+                ActionGroup::builder("game", "game")
+                    .load_order(20)
+                    .action(ActionKind::UpdateDatabase, "data\\units.sql"),
+            )
+            .build()?;
+        let b = Mod::builder("b")
+            .action_group(
+                ActionGroup::builder("game", "game")
+                    .action(ActionKind::UpdateDatabase, "units.xml")
+                    .action(ActionKind::UpdateDatabase, "all.sql"),
+            )
+            .build()?;
+
+        let conflicts =
+            detect_database_conflicts([(&a, a_folder.as_path()), (&b, b_folder.as_path())]);
+        let summary: Vec<String> = conflicts
+            .iter()
+            .map(|conflict| {
+                let touches: Vec<String> = conflict
+                    .touches
+                    .iter()
+                    .map(|touch| format!("{}:{}", touch.mod_id, touch.path))
+                    .collect();
+                let key = conflict.key.as_deref().unwrap_or("*");
+                format!("{}.{key} {}", conflict.table, touches.join(" "))
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                "Units.UNIT_WARRIOR b:units.xml b:all.sql a:data\\units.sql",
+                "Units.UNIT_SCOUT b:all.sql a:data\\units.sql",
+            ]
+        );
+        assert!(conflicts[1].touches[0].table_wide);
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_database_conflicts_duplicate_ids() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let folders: Vec<_> = ["old", "new", "other"]
+            .map(|name| dir.path().join(name))
+            .to_vec();
+        for (folder, file) in folders.iter().zip(["old.sql", "new.sql", "other.sql"]) {
+            fs::create_dir_all(folder)?;
+            fs::write(
+                folder.join(file),
+                "UPDATE Units SET Cost = 1 WHERE UnitType = 'UNIT_X';",
+            )?;
+        }
+        let copy = |file: &str| {
+            Mod::builder("dup")
+                .action_group(
+                    ActionGroup::builder("game", "game").action(ActionKind::UpdateDatabase, file),
+                )
+                .build()
+        };
+        let (old, new) = (copy("old.sql")?, copy("new.sql")?);
+        let other = Mod::builder("other")
+            .action_group(
+                ActionGroup::builder("game", "game")
+                    .action(ActionKind::UpdateDatabase, "other.sql"),
+            )
+            .build()?;
+
+        // Each copy reads its files from its own folder
+        let conflicts = detect_database_conflicts([
+            (&old, folders[0].as_path()),
+            (&new, folders[1].as_path()),
+            (&other, folders[2].as_path()),
+        ]);
+        let paths: Vec<&str> = conflicts[0]
+            .touches
+            .iter()
+            .map(|touch| touch.path.as_str())
+            .collect();
+        assert_eq!(paths, ["old.sql", "new.sql", "other.sql"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,403 @@
+use crate::schema::{ActionGroup, DependencyKind, Mod};
+use crate::version::ModVersion;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// How the version moved between two releases of a mod, compared with
+/// [`ModVersion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum VersionDirection {
+    Upgrade,
+    Downgrade,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VersionChange {
+    pub old: Option<ModVersion>,
+    pub new: Option<ModVersion>,
+    /// `None` when either release has no version.
+    pub direction: Option<VersionDirection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PropertyChange {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyChange {
+    pub id: String,
+    pub kind: DependencyKind,
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CriterionChange {
+    pub id: String,
+    pub change: Change,
+}
+
+/// An action group added, removed, or with a different scope, criteria or
+/// load order. Its files are reported in [`ModDiff::files`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActionGroupChange {
+    pub id: String,
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileChange {
+    pub action_group: String,
+    pub action: String,
+    pub path: String,
+    pub locale: Option<String>,
+    pub change: Change,
+}
+
+/// What an update of a mod changes, see [`diff`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModDiff {
+    /// `None` when both versions are equal, even if written differently,
+    /// e.g. `1.0` and `1`.
+    pub version: Option<VersionChange>,
+    /// The new `AffectsSavedGames` value when it flipped.
+    pub affects_saved_games: Option<bool>,
+    pub properties: Vec<PropertyChange>,
+    pub dependencies: Vec<DependencyChange>,
+    pub criteria: Vec<CriterionChange>,
+    pub action_groups: Vec<ActionGroupChange>,
+    pub files: Vec<FileChange>,
+}
+
+impl ModDiff {
+    pub fn is_empty(&self) -> bool {
+        self.version.is_none()
+            && self.affects_saved_games.is_none()
+            && self.properties.is_empty()
+            && self.dependencies.is_empty()
+            && self.criteria.is_empty()
+            && self.action_groups.is_empty()
+            && self.files.is_empty()
+    }
+
+    /// Whether the update should not be applied without warning the user:
+    /// an `AffectsSavedGames` flip breaks saves made with the other release.
+    pub fn is_critical(&self) -> bool {
+        self.affects_saved_games.is_some()
+    }
+}
+
+/// Pairs up items of both sides by key, in the order of `old` then of the
+/// items only in `new`.
+fn pair_by_key<'a, T, K: PartialEq>(
+    old: &'a [T],
+    new: &'a [T],
+    key: impl Fn(&T) -> K,
+) -> Vec<(Option<&'a T>, Option<&'a T>)> {
+    let find = |items: &'a [T], wanted: &K| items.iter().find(|item| key(item) == *wanted);
+    let mut pairs: Vec<_> = old
+        .iter()
+        .map(|item| (Some(item), find(new, &key(item))))
+        .collect();
+    pairs.extend(
+        new.iter()
+            .filter(|item| find(old, &key(item)).is_none())
+            .map(|item| (None, Some(item))),
+    );
+    pairs
+}
+
+fn change<T: PartialEq>(old: Option<&T>, new: Option<&T>) -> Option<Change> {
+    match (old, new) {
+        (None, Some(_)) => Some(Change::Added),
+        (Some(_), None) => Some(Change::Removed),
+        (Some(old), Some(new)) if old != new => Some(Change::Changed),
+        _ => None,
+    }
+}
+
+fn diff_version(old: &Mod, new: &Mod) -> Option<VersionChange> {
+    let (old, new) = (old.mod_version(), new.mod_version());
+    if old == new {
+        return None;
+    }
+    let direction = match (&old, &new) {
+        (Some(old), Some(new)) if old < new => Some(VersionDirection::Upgrade),
+        (Some(_), Some(_)) => Some(VersionDirection::Downgrade),
+        _ => None,
+    };
+    Some(VersionChange {
+        old,
+        new,
+        direction,
+    })
+}
+
+fn diff_properties(old: &Mod, new: &Mod) -> Vec<PropertyChange> {
+    let collect = |modinfo: &Mod| -> Vec<(String, String)> {
+        modinfo
+            .properties
+            .iter()
+            // Injected by the CivMods app on install, never part of a release
+            .filter(|(name, _)| !name.starts_with("CivMods"))
+            .map(|(name, value)| (name.to_string(), value.into_owned()))
+            .collect()
+    };
+    let (old, new) = (collect(old), collect(new));
+    pair_by_key(&old, &new, |(name, _)| name.clone())
+        .into_iter()
+        .filter(|(old, new)| change(*old, *new).is_some())
+        .map(|(old, new)| PropertyChange {
+            name: old
+                .or(new)
+                .map(|(name, _)| name.clone())
+                .unwrap_or_default(),
+            old: old.map(|(_, value)| value.clone()),
+            new: new.map(|(_, value)| value.clone()),
+        })
+        .collect()
+}
+
+fn diff_dependencies(old: &Mod, new: &Mod) -> Vec<DependencyChange> {
+    let collect = |modinfo: &Mod| -> Vec<(DependencyKind, String)> {
+        modinfo
+            .all_dependencies()
+            .map(|(kind, dependency)| (kind, dependency.id.clone()))
+            .collect()
+    };
+    let (old, new) = (collect(old), collect(new));
+    pair_by_key(&old, &new, Clone::clone)
+        .into_iter()
+        .filter_map(|(old, new)| {
+            let change = change(old, new)?;
+            let (kind, id) = old.or(new)?.clone();
+            Some(DependencyChange { id, kind, change })
+        })
+        .collect()
+}
+
+fn diff_criteria(old: &Mod, new: &Mod) -> Vec<CriterionChange> {
+    pair_by_key(&old.action_criteria, &new.action_criteria, |criterion| {
+        criterion.id.clone()
+    })
+    .into_iter()
+    .filter_map(|(old, new)| {
+        Some(CriterionChange {
+            change: change(old, new)?,
+            id: old.or(new)?.id.clone(),
+        })
+    })
+    .collect()
+}
+
+/// What makes an action group behave differently, leaving out its files.
+fn group_settings(group: &ActionGroup) -> (Option<&str>, Option<&str>, Option<i64>) {
+    (
+        group.scope.as_deref(),
+        group.criteria.as_deref(),
+        group.properties.load_order,
+    )
+}
+
+fn group_files(group: &ActionGroup) -> Vec<(&str, &str, Option<&str>)> {
+    group
+        .actions
+        .iter()
+        .flat_map(|actions| actions.iter())
+        .flat_map(|action| {
+            action.items().map(move |item| {
+                (
+                    action.name.as_str(),
+                    item.path.as_str(),
+                    item.locale.as_deref(),
+                )
+            })
+        })
+        .collect()
+}
+
+fn diff_action_groups(old: &Mod, new: &Mod) -> (Vec<ActionGroupChange>, Vec<FileChange>) {
+    let mut groups = vec![];
+    let mut files = vec![];
+    for (old, new) in pair_by_key(&old.action_groups, &new.action_groups, |group| {
+        group.id.clone()
+    }) {
+        let Some(id) = old.or(new).map(|group| group.id.as_str()) else {
+            continue;
+        };
+        let settings = change(
+            old.map(group_settings).as_ref(),
+            new.map(group_settings).as_ref(),
+        );
+        if let Some(change) = settings {
+            groups.push(ActionGroupChange {
+                id: id.to_string(),
+                change,
+            });
+        }
+
+        let old_files = old.map(group_files).unwrap_or_default();
+        let new_files = new.map(group_files).unwrap_or_default();
+        for (old_file, new_file) in pair_by_key(&old_files, &new_files, |file| *file) {
+            let Some(change) = change(old_file, new_file) else {
+                continue;
+            };
+            let Some(&(action, path, locale)) = old_file.or(new_file) else {
+                continue;
+            };
+            files.push(FileChange {
+                action_group: id.to_string(),
+                action: action.to_string(),
+                path: path.to_string(),
+                locale: locale.map(str::to_string),
+                change,
+            });
+        }
+    }
+    (groups, files)
+}
+
+/// Compares two releases of a mod, e.g. the installed one and an update,
+/// by what matters to players rather than line by line.
+pub fn diff(old: &Mod, new: &Mod) -> ModDiff {
+    let (action_groups, files) = diff_action_groups(old, new);
+    ModDiff {
+        version: diff_version(old, new),
+        affects_saved_games: (old.properties.affects_saved_games
+            != new.properties.affects_saved_games)
+            .then_some(new.properties.affects_saved_games),
+        properties: diff_properties(old, new),
+        dependencies: diff_dependencies(old, new),
+        criteria: diff_criteria(old, new),
+        action_groups,
+        files,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_diff() -> anyhow::Result<()> {
+        let old = Mod::parse(
+            indoc! {r#"
+            <Mod id="a_mod" version="1.2">
+                <Properties>
+                    <Name>A mod</Name>
+                    <AffectsSavedGames>0</AffectsSavedGames>
+                    <CivModsInternalVersionId>42</CivModsInternalVersionId>
+                </Properties>
+                <Dependencies><Mod id="b_mod"/></Dependencies>
+                <ActionCriteria>
+                    <Criteria id="antiquity"><AgeInUse>AGE_ANTIQUITY</AgeInUse></Criteria>
+                    <Criteria id="always"><AlwaysMet/></Criteria>
+                </ActionCriteria>
+                <ActionGroups>
+                    <ActionGroup id="game" scope="game" criteria="always">
+                        <Actions>
+                            <UpdateDatabase><Item>data/units.xml</Item></UpdateDatabase>
+                            <ImportFiles><Item>ui/panel.js</Item></ImportFiles>
+                        </Actions>
+                    </ActionGroup>
+                    <ActionGroup id="shell" scope="shell" criteria="always"/>
+                </ActionGroups>
+            </Mod>
+        "#}
+            .as_bytes(),
+        )?;
+        let new = Mod::parse(indoc! {r#"
+            <Mod id="a_mod" version="1.10">
+                <Properties>
+                    <Name>A mod</Name>
+                    <Description>Now with a description</Description>
+                </Properties>
+                <Dependencies><Mod id="c_mod"/></Dependencies>
+                <ActionCriteria>
+                    <Criteria id="antiquity"><AgeInUse>AGE_EXPLORATION</AgeInUse></Criteria>
+                    <Criteria id="always"><AlwaysMet/></Criteria>
+                </ActionCriteria>
+                <ActionGroups>
+                    <ActionGroup id="game" scope="game" criteria="antiquity">
+                        <Actions>
+                            <UpdateDatabase><Item>data/units.xml</Item><Item>data/civs.xml</Item></UpdateDatabase>
+                        </Actions>
+                    </ActionGroup>
+                    <ActionGroup id="shell" scope="shell" criteria="always"/>
+                </ActionGroups>
+            </Mod>
+        "#}.as_bytes())?;
+
+        let diff = diff(&old, &new);
+        assert_eq!(
+            diff.version.as_ref().and_then(|version| version.direction),
+            Some(VersionDirection::Upgrade)
+        );
+        let respelled = Mod::builder("a_mod").version("1.10.0").build()?;
+        assert_eq!(super::diff(&new, &respelled).version, None);
+        assert_eq!(diff.affects_saved_games, Some(true));
+        assert!(diff.is_critical());
+        assert_eq!(
+            diff.properties,
+            [
+                PropertyChange {
+                    name: "AffectsSavedGames".to_string(),
+                    old: Some("0".to_string()),
+                    new: Some("1".to_string()),
+                },
+                PropertyChange {
+                    name: "Description".to_string(),
+                    old: None,
+                    new: Some("Now with a description".to_string()),
+                },
+            ]
+        );
+        let dependencies: Vec<_> = diff
+            .dependencies
+            .iter()
+            .map(|dependency| (dependency.id.as_str(), dependency.change))
+            .collect();
+        assert_eq!(
+            dependencies,
+            [("b_mod", Change::Removed), ("c_mod", Change::Added)]
+        );
+        assert_eq!(
+            diff.criteria,
+            [CriterionChange {
+                id: "antiquity".to_string(),
+                change: Change::Changed,
+            }]
+        );
+        assert_eq!(
+            diff.action_groups,
+            [ActionGroupChange {
+                id: "game".to_string(),
+                change: Change::Changed,
+            }]
+        );
+        let files: Vec<_> = diff
+            .files
+            .iter()
+            .map(|file| (file.action.as_str(), file.path.as_str(), file.change))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                ("ImportFiles", "ui/panel.js", Change::Removed),
+                ("UpdateDatabase", "data/civs.xml", Change::Added),
+            ]
+        );
+
+        assert!(super::diff(&new, &new).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,602 @@
+use crate::Mod;
+use crate::error::ModinfoError;
+use crate::sanitize::Sanitizer;
+use quick_xml::escape;
+use quick_xml::events::{BytesStart, Event};
+use std::fmt;
+use std::ops::Range;
+
+/// Order of the sections of `<Mod>` in the game's own files, used when a
+/// missing section has to be added.
+const SECTION_ORDER: &[&str] = &[
+    "Properties",
+    "Dependencies",
+    "References",
+    "ActionCriteria",
+    "ActionGroups",
+    "LocalizedText",
+];
+
+/// Why a [`Document`] edit was refused. The document is left unchanged.
+#[derive(Debug)]
+pub enum EditError {
+    /// Not a valid XML element name, e.g. `My Property`.
+    InvalidName(String),
+    /// Comments cannot contain `--` nor end with `-`.
+    InvalidComment(String),
+    /// Dependencies need a mod id.
+    EmptyId,
+    /// The edit would leave a document that cannot be read back.
+    Unreadable(ModinfoError),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::InvalidName(name) => write!(f, "Invalid element name '{name}'"),
+            EditError::InvalidComment(comment) => write!(f, "Invalid comment '{comment}'"),
+            EditError::EmptyId => write!(f, "Empty mod id"),
+            EditError::Unreadable(error) => write!(f, "Edit breaks the document: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// Whether `name` can be written as an element name. Non-ASCII characters
+/// are all accepted, as in [`crate::sanitize`].
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':' || !c.is_ascii())
+        && chars.all(|c| {
+            c.is_ascii_alphanumeric() || matches!(c, '_' | ':' | '-' | '.') || !c.is_ascii()
+        })
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    /// From the `<` of the start tag to the `>` of the end tag.
+    span: Range<usize>,
+    /// Between the start and end tags, `None` for `<Empty/>` elements.
+    content: Option<Range<usize>>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn new(tag: &BytesStart, span: Range<usize>, content: Option<Range<usize>>) -> Self {
+        let attributes = tag
+            .html_attributes()
+            .flatten()
+            .map(|attribute| {
+                let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+                let value = String::from_utf8_lossy(&attribute.value);
+                let value = escape::unescape(&value).map_or(value.to_string(), |v| v.to_string());
+                (key, value)
+            })
+            .collect();
+        Self {
+            name: String::from_utf8_lossy(tag.name().as_ref()).to_string(),
+            attributes,
+            span,
+            content,
+            children: vec![],
+        }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn child_with_id(&self, name: &str, id: &str) -> Option<&Element> {
+        self.children
+            .iter()
+            .find(|child| child.name == name && child.attribute("id") == Some(id))
+    }
+}
+
+/// A modinfo file that can be edited without touching anything but the
+/// edited elements: comments, whitespace, attribute order and unknown
+/// elements are kept byte for byte.
+///
+/// Inserted elements follow the indentation and line endings of the file.
+#[derive(Debug, Clone)]
+pub struct Document {
+    source: String,
+    root: Element,
+}
+
+impl Document {
+    /// Reads the element tree of `source`. Malformed XML is read the same way
+    /// as [`Mod::parse`] does, but left as is.
+    pub fn parse(source: impl Into<String>) -> Result<Self, ModinfoError> {
+        let source = source.into();
+        let root = Self::read_root(&source)?;
+        Ok(Self { source, root })
+    }
+
+    fn read_root(source: &str) -> Result<Element, ModinfoError> {
+        let mut sanitizer = Sanitizer::new(source.as_bytes());
+        let mut open: Vec<Element> = vec![];
+        let mut root = None;
+        loop {
+            let event = sanitizer.next_event().map_err(|error| {
+                let offset = sanitizer.error_position().offset;
+                ModinfoError::from_xml(error, source.as_bytes(), Some(offset))
+            })?;
+            let span = sanitizer.span();
+            let element = match event {
+                Event::Start(tag) => {
+                    open.push(Element::new(&tag, span.clone(), Some(span.end..span.end)));
+                    continue;
+                }
+                Event::Empty(tag) => Element::new(&tag, span, None),
+                Event::End(_) => {
+                    let Some(mut element) = open.pop() else {
+                        continue;
+                    };
+                    element.span.end = span.end;
+                    if let Some(content) = &mut element.content {
+                        content.end = span.start;
+                    }
+                    element
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+            match open.last_mut() {
+                Some(parent) => parent.children.push(element),
+                None => {
+                    root.get_or_insert(element);
+                }
+            }
+        }
+        root.ok_or_else(|| ModinfoError::Schema {
+            position: None,
+            message: "no root element".to_string(),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn into_string(self) -> String {
+        self.source
+    }
+
+    /// Parses the edited document.
+    pub fn to_mod(&self) -> Result<Mod, ModinfoError> {
+        Mod::parse(self.source.as_bytes())
+    }
+
+    /// Replaces `range` of the source and reads the element tree again.
+    fn splice(&mut self, range: Range<usize>, text: &str) -> Result<(), EditError> {
+        let mut source = self.source.clone();
+        source.replace_range(range, text);
+        self.root = Self::read_root(&source).map_err(EditError::Unreadable)?;
+        self.source = source;
+        Ok(())
+    }
+
+    fn newline(&self) -> &'static str {
+        if self.source.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    /// Whitespace before `offset` on its line, `None` if there is anything
+    /// else, as in files written on a single line.
+    fn line_indent(&self, offset: usize) -> Option<&str> {
+        let line_start = self.source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let indent = &self.source[line_start..offset];
+        indent
+            .chars()
+            .all(|c| c == ' ' || c == '\t')
+            .then_some(indent)
+    }
+
+    /// One level of indentation, as found between a parent and its children.
+    fn indent_unit(&self) -> String {
+        fn find(document: &Document, element: &Element) -> Option<String> {
+            let indent = document.line_indent(element.span.start)?;
+            element.children.iter().find_map(|child| {
+                let child_indent = document.line_indent(child.span.start)?;
+                match child_indent.strip_prefix(indent) {
+                    Some(unit) if !unit.is_empty() => Some(unit.to_string()),
+                    _ => find(document, child),
+                }
+            })
+        }
+        find(self, &self.root).unwrap_or_else(|| "\t".to_string())
+    }
+
+    /// Inserts `markup` as a child of `parent`, after the element `after` or as
+    /// the first child, on its own line unless the parent is written on a
+    /// single line.
+    fn insert(
+        &mut self,
+        parent: &Element,
+        after: Option<&Element>,
+        markup: &str,
+    ) -> Result<(), EditError> {
+        let newline = self.newline();
+        let parent_indent = self.line_indent(parent.span.start).map(str::to_string);
+        let child_indent = parent_indent
+            .as_ref()
+            .map(|indent| format!("{indent}{}", self.indent_unit()));
+
+        let Some(content) = &parent.content else {
+            // `<Parent/>` becomes `<Parent>markup</Parent>`
+            let start_tag = self.source[parent.span.start..parent.span.end - 2].trim_end();
+            let text = match (&parent_indent, &child_indent) {
+                (Some(parent_indent), Some(child_indent)) => format!(
+                    "{start_tag}>{newline}{child_indent}{markup}{newline}{parent_indent}</{}>",
+                    parent.name
+                ),
+                _ => format!("{start_tag}>{markup}</{}>", parent.name),
+            };
+            return self.splice(parent.span.clone(), &text);
+        };
+
+        match (after, parent.children.first()) {
+            (Some(sibling), _) => {
+                let text = match self.line_indent(sibling.span.start) {
+                    Some(indent) => format!("{newline}{indent}{markup}"),
+                    None => markup.to_string(),
+                };
+                self.splice(sibling.span.end..sibling.span.end, &text)
+            }
+            (None, Some(first)) => {
+                let text = match self.line_indent(first.span.start) {
+                    Some(indent) => format!("{markup}{newline}{indent}"),
+                    None => markup.to_string(),
+                };
+                self.splice(first.span.start..first.span.start, &text)
+            }
+            (None, None) => {
+                let blank = self.source[content.clone()].trim().is_empty();
+                match (&parent_indent, &child_indent) {
+                    (Some(parent_indent), Some(child_indent)) if blank => {
+                        let text =
+                            format!("{newline}{child_indent}{markup}{newline}{parent_indent}");
+                        self.splice(content.clone(), &text)
+                    }
+                    _ => self.splice(content.end..content.end, markup),
+                }
+            }
+        }
+    }
+
+    /// Inserts `markup` at the end of `parent`, after any comment or text.
+    fn append(&mut self, parent: &Element, markup: &str) -> Result<(), EditError> {
+        let Some(content) = &parent.content else {
+            return self.insert(parent, None, markup);
+        };
+        let text = &self.source[content.clone()];
+        let end = content.start + text.trim_end().len();
+        if end == content.start {
+            return self.insert(parent, None, markup);
+        }
+        let indent = match parent.children.last() {
+            Some(last) => self.line_indent(last.span.start).map(str::to_string),
+            None => self
+                .line_indent(parent.span.start)
+                .map(|indent| format!("{indent}{}", self.indent_unit())),
+        };
+        let text = match indent {
+            Some(indent) if text.contains('\n') => format!("{}{indent}{markup}", self.newline()),
+            _ => markup.to_string(),
+        };
+        self.splice(end..end, &text)
+    }
+
+    /// Removes `element`, and the line it was on if it was alone on it.
+    fn remove(&mut self, element: &Element) -> Result<(), EditError> {
+        let mut range = element.span.clone();
+        let rest_of_line = self.source[range.end..].split('\n').next().unwrap_or("");
+        if let Some(indent) = self.line_indent(range.start)
+            && rest_of_line.trim().is_empty()
+        {
+            range.start -= indent.len();
+            if self.source[..range.start].ends_with('\n') {
+                range.start -= 1;
+                if self.source[..range.start].ends_with('\r') {
+                    range.start -= 1;
+                }
+            }
+        }
+        self.splice(range, "")
+    }
+
+    /// Replaces the text content of `element`.
+    fn set_text(&mut self, element: &Element, value: &str) -> Result<(), EditError> {
+        let value = escape::partial_escape(value);
+        match &element.content {
+            Some(content) => self.splice(content.clone(), &value),
+            None => {
+                let start_tag = self.source[element.span.start..element.span.end - 2].trim_end();
+                let text = format!("{start_tag}>{value}</{}>", element.name);
+                self.splice(element.span.clone(), &text)
+            }
+        }
+    }
+
+    /// Returns the `name` section of `<Mod>`, adding it if needed.
+    fn section(&mut self, name: &str) -> Result<Element, EditError> {
+        if let Some(section) = self.root.child(name) {
+            return Ok(section.clone());
+        }
+        let position = SECTION_ORDER.iter().position(|section| *section == name);
+        let root = self.root.clone();
+        let after = root.children.iter().rev().find(|child| {
+            let child_position = SECTION_ORDER
+                .iter()
+                .position(|section| *section == child.name);
+            match (child_position, position) {
+                (Some(child_position), Some(position)) => child_position < position,
+                _ => false,
+            }
+        });
+        let after = match position {
+            Some(_) => after,
+            None => root.children.last(),
+        };
+        self.insert(&root, after, &format!("<{name}></{name}>"))?;
+        Ok(self
+            .root
+            .child(name)
+            .cloned()
+            .expect("section was just added"))
+    }
+
+    /// Whether `<Properties>` has a `<{name}>` element, whether or not the
+    /// rest of the document fits the schema.
+    pub fn has_property(&self, name: &str) -> bool {
+        self.root
+            .child("Properties")
+            .is_some_and(|properties| properties.child(name).is_some())
+    }
+
+    /// Sets the text of `<Properties><{name}>`, adding the property if needed.
+    pub fn set_property(&mut self, name: &str, value: &str) -> Result<(), EditError> {
+        if !is_xml_name(name) {
+            return Err(EditError::InvalidName(name.to_string()));
+        }
+        let properties = self.section("Properties")?;
+        match properties.child(name) {
+            Some(property) => self.set_text(property, value),
+            None => {
+                let value = escape::partial_escape(value);
+                self.append(&properties, &format!("<{name}>{value}</{name}>"))
+            }
+        }
+    }
+
+    /// Removes `<Properties><{name}>`. Returns `false` if there was none.
+    pub fn remove_property(&mut self, name: &str) -> Result<bool, EditError> {
+        let Some(property) = self
+            .root
+            .child("Properties")
+            .and_then(|properties| properties.child(name))
+            .cloned()
+        else {
+            return Ok(false);
+        };
+        self.remove(&property)?;
+        Ok(true)
+    }
+
+    /// Adds `<!--{comment}-->` at the end of `<Properties>`.
+    pub fn add_property_comment(&mut self, comment: &str) -> Result<(), EditError> {
+        if comment.contains("--") || comment.ends_with('-') {
+            return Err(EditError::InvalidComment(comment.to_string()));
+        }
+        let properties = self.section("Properties")?;
+        self.append(&properties, &format!("<!--{comment}-->"))
+    }
+
+    /// Adds `<Mod id="..." title="..."/>` to `<Dependencies>`. Returns `false`
+    /// if the mod already depends on `id`.
+    pub fn add_dependency(&mut self, id: &str, title: Option<&str>) -> Result<bool, EditError> {
+        if id.trim().is_empty() {
+            return Err(EditError::EmptyId);
+        }
+        let dependencies = self.section("Dependencies")?;
+        if dependencies.child_with_id("Mod", id).is_some() {
+            return Ok(false);
+        }
+        let mut markup = format!("<Mod id=\"{}\"", escape::escape(id));
+        if let Some(title) = title {
+            markup.push_str(&format!(" title=\"{}\"", escape::escape(title)));
+        }
+        markup.push_str("/>");
+        self.append(&dependencies, &markup)?;
+        Ok(true)
+    }
+
+    fn action_group(&self, id: &str) -> Option<&Element> {
+        self.root
+            .child("ActionGroups")?
+            .child_with_id("ActionGroup", id)
+    }
+
+    /// Sets `<LoadOrder>` of the action group `id`, adding its `<Properties>`
+    /// if needed. Returns `false` if there is no such action group.
+    pub fn set_load_order(
+        &mut self,
+        action_group: &str,
+        load_order: i64,
+    ) -> Result<bool, EditError> {
+        let Some(group) = self.action_group(action_group).cloned() else {
+            return Ok(false);
+        };
+        let properties = match group.child("Properties") {
+            Some(properties) => properties.clone(),
+            None => {
+                self.insert(&group, None, "<Properties></Properties>")?;
+                let group = self.action_group(action_group).expect("group still exists");
+                group.child("Properties").cloned().expect("was just added")
+            }
+        };
+        match properties.child("LoadOrder") {
+            Some(element) => self.set_text(element, &load_order.to_string())?,
+            None => self.append(&properties, &format!("<LoadOrder>{load_order}</LoadOrder>"))?,
+        }
+        Ok(true)
+    }
+
+    /// Removes the action group `id`. Returns `false` if there was none.
+    pub fn remove_action_group(&mut self, id: &str) -> Result<bool, EditError> {
+        let Some(group) = self.action_group(id).cloned() else {
+            return Ok(false);
+        };
+        self.remove(&group)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const MODINFO: &str = indoc! {r#"
+        <?xml version="1.0" encoding="utf-8"?>
+        <Mod id="a_mod" version="1" xmlns="ModInfo">
+          <!-- Keep me -->
+          <Properties>
+            <Name>A &amp; B</Name>
+            <Unknown   attr='x' >kept</Unknown>
+          </Properties>
+          <Dependencies/>
+          <ActionGroups>
+            <ActionGroup id="shell" scope="shell">
+              <Actions/>
+            </ActionGroup>
+            <ActionGroup id="game" scope="game">
+              <Properties>
+                <LoadOrder>10</LoadOrder>
+              </Properties>
+            </ActionGroup>
+          </ActionGroups>
+        </Mod>
+    "#};
+
+    #[test]
+    fn test_edit_document() -> anyhow::Result<()> {
+        let mut document = Document::parse(MODINFO)?;
+        document.set_property("Name", "C & D")?;
+        document.set_property("Version", "2")?;
+        assert!(document.add_dependency("base-standard", Some("LOC_BASE"))?);
+        assert!(!document.add_dependency("base-standard", None)?);
+        assert!(document.set_load_order("game", 11)?);
+        assert!(document.set_load_order("shell", -5)?);
+        assert!(document.remove_action_group("game")?);
+        assert!(!document.remove_action_group("game")?);
+
+        assert_eq!(
+            document.as_str(),
+            indoc! {r#"
+                <?xml version="1.0" encoding="utf-8"?>
+                <Mod id="a_mod" version="1" xmlns="ModInfo">
+                  <!-- Keep me -->
+                  <Properties>
+                    <Name>C &amp; D</Name>
+                    <Unknown   attr='x' >kept</Unknown>
+                    <Version>2</Version>
+                  </Properties>
+                  <Dependencies>
+                    <Mod id="base-standard" title="LOC_BASE"/>
+                  </Dependencies>
+                  <ActionGroups>
+                    <ActionGroup id="shell" scope="shell">
+                      <Properties>
+                        <LoadOrder>-5</LoadOrder>
+                      </Properties>
+                      <Actions/>
+                    </ActionGroup>
+                  </ActionGroups>
+                </Mod>
+            "#}
+        );
+
+        let edited = document.to_mod()?;
+        assert_eq!(edited.properties.name.as_deref(), Some("C & D"));
+        assert_eq!(edited.action_groups[0].properties.load_order, Some(-5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_missing_sections() -> anyhow::Result<()> {
+        let mut document = Document::parse("<Mod id=\"a_mod\">\r\n\t<ActionGroups/>\r\n</Mod>")?;
+        document.set_property("Name", "A")?;
+        document.add_dependency("core", None)?;
+        assert_eq!(
+            document.as_str(),
+            "<Mod id=\"a_mod\">\r\n\t<Properties>\r\n\t\t<Name>A</Name>\r\n\t</Properties>\
+            \r\n\t<Dependencies>\r\n\t\t<Mod id=\"core\"/>\r\n\t</Dependencies>\
+            \r\n\t<ActionGroups/>\r\n</Mod>"
+        );
+        assert!(document.has_property("Name"));
+        assert!(document.remove_property("Name")?);
+        assert!(!document.remove_property("Name")?);
+        assert!(!document.has_property("Name"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_single_line() -> anyhow::Result<()> {
+        let mut document =
+            Document::parse(r#"<Mod id="a_mod"><Properties><Name>A</Name></Properties></Mod>"#)?;
+        document.add_property_comment(" Added ")?;
+        document.set_property("URL", "https://example.com")?;
+        assert_eq!(
+            document.as_str(),
+            r#"<Mod id="a_mod"><Properties><Name>A</Name><!-- Added --><URL>https://example.com</URL></Properties></Mod>"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_invalid() -> anyhow::Result<()> {
+        let mut document = Document::parse(MODINFO)?;
+        for name in ["", "My Property", "1st", "Name><Evil", "A/"] {
+            assert!(
+                matches!(
+                    document.set_property(name, "x"),
+                    Err(EditError::InvalidName(_))
+                ),
+                "{name}"
+            );
+        }
+        for comment in ["a -- b", "trailing-"] {
+            assert!(matches!(
+                document.add_property_comment(comment),
+                Err(EditError::InvalidComment(_))
+            ));
+        }
+        assert!(matches!(
+            document.add_dependency(" ", None),
+            Err(EditError::EmptyId)
+        ));
+        assert_eq!(document.as_str(), MODINFO);
+
+        document.set_property("Über.Name_2", "ok")?;
+        document.add_dependency("a\"<b>", None)?;
+        assert_eq!(document.to_mod()?.dependencies[0].id, "a\"<b>");
+        Ok(())
+    }
+}
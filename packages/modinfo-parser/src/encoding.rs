@@ -0,0 +1,272 @@
+use crate::error::ModinfoError;
+use crate::sanitize::{Repair, RepairKind, TextPosition};
+use encoding_rs::{DecoderResult, Encoding, UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+/// Encoding of a modinfo file, as found by [`decode`]. Writers should use it
+/// to save the file back the way the author had it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetectedEncoding {
+    pub encoding: &'static Encoding,
+    /// Whether the file starts with a byte order mark.
+    pub bom: bool,
+}
+
+impl DetectedEncoding {
+    /// Name of the encoding, e.g. `UTF-8` or `windows-1252`.
+    pub fn name(&self) -> &'static str {
+        self.encoding.name()
+    }
+}
+
+impl Default for DetectedEncoding {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            bom: false,
+        }
+    }
+}
+
+/// Value of the `encoding` pseudo-attribute of the XML declaration, if any.
+/// Only works for ASCII compatible encodings, which is all a declaration can
+/// tell apart from the first bytes anyway.
+fn declared_encoding(source: &[u8]) -> Option<&[u8]> {
+    let declaration = source.strip_prefix(b"<?xml")?;
+    let declaration = &declaration[..declaration.windows(2).position(|w| w == b"?>")?];
+    let start = declaration.windows(8).position(|w| w == b"encoding")? + 8;
+    let value = declaration[start..].trim_ascii_start().strip_prefix(b"=")?;
+    let value = value.trim_ascii_start();
+    let quote = *value
+        .first()
+        .filter(|quote| matches!(quote, b'"' | b'\''))?;
+    let value = &value[1..];
+    Some(&value[..value.iter().position(|b| *b == quote)?])
+}
+
+/// Finds the encoding of `source`, from its byte order mark, the first bytes
+/// of a UTF-16 document, or its XML declaration. Files without any of these
+/// are UTF-8 when valid, and Windows-1252 otherwise: most community mods are
+/// written on Windows, where that used to be the default. A file declared as
+/// UTF-8 stays UTF-8, even with a few invalid bytes.
+pub fn detect(source: &[u8]) -> DetectedEncoding {
+    if let Some((encoding, _)) = Encoding::for_bom(source) {
+        return DetectedEncoding {
+            encoding,
+            bom: true,
+        };
+    }
+    let encoding = match source {
+        [b'<', 0, ..] => UTF_16LE,
+        [0, b'<', ..] => UTF_16BE,
+        _ => match declared_encoding(source)
+            .and_then(Encoding::for_label)
+            // A declaration readable as ASCII cannot be in UTF-16
+            .filter(|encoding| encoding.is_ascii_compatible())
+        {
+            Some(encoding) => encoding,
+            None if std::str::from_utf8(source).is_ok() => UTF_8,
+            None => WINDOWS_1252,
+        },
+    };
+    DetectedEncoding {
+        encoding,
+        bom: false,
+    }
+}
+
+/// Decodes `source` to UTF-8, returning the encoding it was in. Invalid
+/// UTF-8 is replaced with U+FFFD and recorded as a [`Repair`], other encodings
+/// fail on byte sequences that are invalid in them.
+///
+/// Positions, in errors and repairs, refer to the decoded text.
+pub fn decode(source: &[u8]) -> Result<(String, DetectedEncoding, Vec<Repair>), ModinfoError> {
+    let detected = detect(source);
+    let bom_length = if detected.bom {
+        Encoding::for_bom(source).map_or(0, |(_, length)| length)
+    } else {
+        0
+    };
+    let bytes = &source[bom_length..];
+
+    if detected.encoding == UTF_8 {
+        let (text, repairs) = decode_utf8_lossy(bytes);
+        return Ok((text, detected, repairs));
+    }
+
+    let mut decoder = detected.encoding.new_decoder_without_bom_handling();
+    let mut text = String::with_capacity(
+        decoder
+            .max_utf8_buffer_length_without_replacement(bytes.len())
+            .unwrap_or(bytes.len()),
+    );
+    let (result, _) = decoder.decode_to_string_without_replacement(bytes, &mut text, true);
+    match result {
+        DecoderResult::InputEmpty => Ok((text, detected, vec![])),
+        // `text` stops right before the malformed sequence
+        DecoderResult::Malformed(..) => Err(ModinfoError::Encoding {
+            position: Some(TextPosition::locate(text.as_bytes(), text.len())),
+            message: format!("invalid {} byte sequence", detected.name()),
+        }),
+        DecoderResult::OutputFull => unreachable!("the buffer is sized for the worst case"),
+    }
+}
+
+/// Encodes `text` back to `encoding`, with a byte order mark if it had one.
+/// Characters the encoding cannot represent are written as character
+/// references.
+pub fn encode(text: &str, encoding: DetectedEncoding) -> Vec<u8> {
+    let DetectedEncoding { encoding, bom } = encoding;
+    let mut bytes: Vec<u8> = if bom {
+        match encoding {
+            e if e == UTF_16LE => b"\xFF\xFE".to_vec(),
+            e if e == UTF_16BE => b"\xFE\xFF".to_vec(),
+            _ => b"\xEF\xBB\xBF".to_vec(),
+        }
+    } else {
+        vec![]
+    };
+    // encoding_rs only decodes UTF-16
+    if encoding == UTF_16LE {
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    } else if encoding == UTF_16BE {
+        bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+    } else {
+        bytes.extend_from_slice(&encoding.encode(text).0);
+    }
+    bytes
+}
+
+/// Like [`String::from_utf8_lossy`], recording every replaced byte sequence.
+fn decode_utf8_lossy(bytes: &[u8]) -> (String, Vec<Repair>) {
+    let mut text = String::with_capacity(bytes.len());
+    let mut repairs = vec![];
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        let invalid = chunk.invalid();
+        if invalid.is_empty() {
+            continue;
+        }
+        let repair = Repair {
+            kind: RepairKind::InvalidByteSequence,
+            position: TextPosition::locate(text.as_bytes(), text.len()),
+            original: invalid.iter().map(|b| format!("\\x{b:02X}")).collect(),
+            replacement: char::REPLACEMENT_CHARACTER.to_string(),
+        };
+        log::warn!(
+            "{:?} at {}:{}: {:?}",
+            repair.kind,
+            repair.position.line,
+            repair.position.column,
+            repair.original
+        );
+        repairs.push(repair);
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    (text, repairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::SHIFT_JIS;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_detect() {
+        let detect_str = |source: &[u8]| {
+            let detected = detect(source);
+            (detected.name(), detected.bom)
+        };
+        assert_eq!(detect_str(b"<Mod/>"), ("UTF-8", false));
+        assert_eq!(detect_str(b"\xEF\xBB\xBF<Mod/>"), ("UTF-8", true));
+        assert_eq!(detect_str(b"\xFF\xFE<\0M\0"), ("UTF-16LE", true));
+        assert_eq!(detect_str(b"\0<\0M"), ("UTF-16BE", false));
+        assert_eq!(
+            detect_str(br#"<?xml version="1.0" encoding='Shift_JIS'?><Mod/>"#),
+            ("Shift_JIS", false)
+        );
+        assert_eq!(
+            detect_str(b"<?xml version=\"1.0\" encoding=\"utf-8\"?><Name>Andr\xE9</Name>"),
+            ("UTF-8", false)
+        );
+        assert_eq!(
+            detect_str(b"<?xml version=\"1.0\"?><Name>Andr\xE9</Name>"),
+            ("windows-1252", false)
+        );
+    }
+
+    #[test]
+    fn test_decode() -> anyhow::Result<()> {
+        let mut source = b"\xFF\xFE".to_vec();
+        source.extend(utf16le(
+            r#"<?xml version="1.0" encoding="utf-16"?><Name>Ünïcödé</Name>"#,
+        ));
+        let (text, detected, _) = decode(&source)?;
+        assert_eq!(
+            text,
+            r#"<?xml version="1.0" encoding="utf-16"?><Name>Ünïcödé</Name>"#
+        );
+        assert_eq!(detected.encoding, UTF_16LE);
+
+        let (text, ..) = decode(b"<Name>Andr\xE9</Name>")?;
+        assert_eq!(text, "<Name>Andr\u{e9}</Name>");
+
+        let mut source = br#"<?xml version="1.0" encoding="shift_jis"?>"#.to_vec();
+        source.extend(SHIFT_JIS.encode("\n<Name>文明</Name>").0.iter());
+        let (text, ..) = decode(&source)?;
+        assert!(text.ends_with("<Name>文明</Name>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let mut source = br#"<?xml version="1.0" encoding="shift_jis"?>"#.to_vec();
+        source.extend(SHIFT_JIS.encode("\n<Name>文明").0.iter());
+        source.extend(b"\x81\x20</Name>");
+        let error = decode(&source).unwrap_err();
+        let position = error.position().unwrap();
+        // Two characters into the name: six bytes of UTF-8, four of Shift_JIS
+        assert_eq!((position.line, position.column), (2, 9));
+        assert_eq!(position.offset, 55);
+    }
+
+    #[test]
+    fn test_decode_invalid_utf8() -> anyhow::Result<()> {
+        let mut source = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<Name>文明 </Name>"
+            .as_bytes()
+            .to_vec();
+        source.splice(52..52, [0xE9, 0xFF]);
+        let (text, detected, repairs) = decode(&source)?;
+        assert_eq!(detected.encoding, UTF_8);
+        assert!(text.ends_with("<Name>文明 \u{FFFD}\u{FFFD}</Name>"));
+        assert_eq!(
+            repairs,
+            vec![
+                Repair {
+                    kind: RepairKind::InvalidByteSequence,
+                    position: TextPosition {
+                        offset: 52,
+                        line: 2,
+                        column: 10,
+                    },
+                    original: "\\xE9".to_string(),
+                    replacement: "\u{FFFD}".to_string(),
+                },
+                Repair {
+                    kind: RepairKind::InvalidByteSequence,
+                    position: TextPosition {
+                        offset: 55,
+                        line: 2,
+                        column: 11,
+                    },
+                    original: "\\xFF".to_string(),
+                    replacement: "\u{FFFD}".to_string(),
+                },
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use crate::sanitize::TextPosition;
+use std::{fmt, io};
+
+/// Why a modinfo file could not be read. Positions refer to the source
+/// decoded to UTF-8, before any repair.
+#[derive(Debug)]
+pub enum ModinfoError {
+    /// The file could not be read at all.
+    Io(io::Error),
+    /// The bytes are not valid text in the encoding of the file.
+    Encoding {
+        position: Option<TextPosition>,
+        message: String,
+    },
+    /// The XML is broken beyond what the sanitizer can repair.
+    Xml {
+        position: Option<TextPosition>,
+        error: quick_xml::Error,
+    },
+    /// The XML is well-formed but does not describe a mod, e.g. the `<Mod>`
+    /// element has no `id` attribute.
+    Schema {
+        position: Option<TextPosition>,
+        message: String,
+    },
+}
+
+impl ModinfoError {
+    pub fn position(&self) -> Option<TextPosition> {
+        match self {
+            ModinfoError::Io(_) => None,
+            ModinfoError::Encoding { position, .. }
+            | ModinfoError::Xml { position, .. }
+            | ModinfoError::Schema { position, .. } => *position,
+        }
+    }
+
+    /// Error raised while reading `source`, located at `offset` when known.
+    pub(crate) fn from_xml(error: quick_xml::Error, source: &[u8], offset: Option<usize>) -> Self {
+        let position = offset.map(|offset| TextPosition::locate(source, offset));
+        match error {
+            quick_xml::Error::Io(error) => match std::sync::Arc::try_unwrap(error) {
+                Ok(error) => ModinfoError::Io(error),
+                Err(error) => ModinfoError::Io(io::Error::new(error.kind(), error.to_string())),
+            },
+            quick_xml::Error::Encoding(error) => ModinfoError::Encoding {
+                position,
+                message: error.to_string(),
+            },
+            error => ModinfoError::Xml { position, error },
+        }
+    }
+}
+
+impl From<io::Error> for ModinfoError {
+    fn from(error: io::Error) -> Self {
+        ModinfoError::Io(error)
+    }
+}
+
+/// Errors of the deserializer have no position: it reads the sanitized
+/// output, not the original source.
+impl From<quick_xml::DeError> for ModinfoError {
+    fn from(error: quick_xml::DeError) -> Self {
+        match error {
+            quick_xml::DeError::InvalidXml(error) => ModinfoError::from_xml(error, &[], None),
+            error => ModinfoError::Schema {
+                position: None,
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ModinfoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModinfoError::Io(error) => write!(f, "Cannot read modinfo: {error}")?,
+            ModinfoError::Encoding { message, .. } => write!(f, "Invalid encoding: {message}")?,
+            ModinfoError::Xml { error, .. } => write!(f, "Invalid XML: {error}")?,
+            ModinfoError::Schema { message, .. } => write!(f, "Invalid modinfo: {message}")?,
+        }
+        if let Some(position) = self.position() {
+            write!(f, " at {}:{}", position.line, position.column)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ModinfoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ModinfoError::Io(error) => Some(error),
+            ModinfoError::Xml { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::Mod;
+use crate::encoding::decode;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Files ignored when looking at a mod folder. Make sure this is aligned with
+/// the ignore list in the backend in Node.js.
+pub fn is_hidden_file_name(file_name: &str) -> bool {
+    file_name.starts_with('.')
+        || file_name.eq_ignore_ascii_case("__MACOSX")
+        || file_name.eq_ignore_ascii_case("thumbs.db")
+}
+
+/// A file path referenced by the modinfo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReference {
+    /// `None` for `<LocalizedText>` entries.
+    pub action_group: Option<String>,
+    /// Element name, e.g. `UpdateDatabase` or `LocalizedText`.
+    pub action: String,
+    pub path: String,
+}
+
+/// A reference which only resolves when ignoring case, so it works on Windows
+/// but not on case-sensitive file systems (Linux, Proton).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrongCaseFile {
+    pub reference: FileReference,
+    /// Path on disk, relative to the mod folder.
+    pub actual_path: String,
+}
+
+/// Result of [`Mod::check_files`]. All paths are relative to the mod folder,
+/// with `/` separators.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FileReport {
+    pub missing: Vec<FileReference>,
+    pub wrong_case: Vec<WrongCaseFile>,
+    pub unreferenced: Vec<String>,
+}
+
+impl FileReport {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.wrong_case.is_empty() && self.unreferenced.is_empty()
+    }
+}
+
+pub(crate) fn normalize_path(path: &str) -> String {
+    let path = path.trim().replace('\\', "/");
+    path.trim_start_matches("./").to_string()
+}
+
+/// Reads and decodes a file referenced by a modinfo, returning its path on
+/// disk too. Failures are logged, as the game skips unreadable files as well.
+pub(crate) fn read_mod_file(mod_folder: &Path, path: &str) -> Option<(PathBuf, String)> {
+    let file_path = mod_folder.join(normalize_path(path));
+    let bytes = fs::read(&file_path)
+        .map_err(|err| log::warn!("Cannot read {}: {err}", file_path.display()))
+        .ok()?;
+    let (text, ..) = decode(&bytes)
+        .map_err(|err| log::warn!("Cannot decode {}: {err}", file_path.display()))
+        .ok()?;
+    Some((file_path, text))
+}
+
+impl Mod {
+    /// Iterates over every file referenced by action items and `<LocalizedText>`.
+    pub fn referenced_files(&self) -> impl Iterator<Item = FileReference> + '_ {
+        let actions = self.action_groups.iter().flat_map(|group| {
+            group
+                .actions
+                .iter()
+                .flat_map(|actions| actions.iter())
+                .flat_map(move |action| {
+                    action.items().map(move |item| FileReference {
+                        action_group: Some(group.id.clone()),
+                        action: action.name.clone(),
+                        path: item.path.clone(),
+                    })
+                })
+        });
+        let localized_text = self.localized_text.iter().map(|file| FileReference {
+            action_group: None,
+            action: "LocalizedText".to_string(),
+            path: file.clone(),
+        });
+        actions.chain(localized_text)
+    }
+
+    /// Compares the files referenced by the modinfo with the content of
+    /// `mod_folder`, the folder containing the `.modinfo` file.
+    pub fn check_files(&self, mod_folder: impl AsRef<Path>) -> io::Result<FileReport> {
+        let mod_folder = mod_folder.as_ref();
+        let mut on_disk = vec![];
+        for entry in WalkDir::new(mod_folder)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| {
+                e.depth() == 0 || !is_hidden_file_name(&e.file_name().to_string_lossy())
+            })
+        {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry
+                .path()
+                .strip_prefix(mod_folder)
+                .unwrap_or(entry.path());
+            let relative = normalize_path(&relative.to_string_lossy());
+            // The modinfo itself, and possibly variants of it
+            if relative.to_lowercase().ends_with(".modinfo") {
+                continue;
+            }
+            on_disk.push(relative);
+        }
+
+        let exact: HashSet<&str> = on_disk.iter().map(String::as_str).collect();
+        let mut lowercase: HashMap<String, &str> = HashMap::new();
+        for path in &on_disk {
+            lowercase.entry(path.to_lowercase()).or_insert(path);
+        }
+
+        let mut report = FileReport::default();
+        let mut referenced = HashSet::new();
+        for reference in self.referenced_files() {
+            let path = normalize_path(&reference.path);
+            if path.is_empty() {
+                // Reported by `Mod::validate`
+                continue;
+            }
+            if exact.contains(path.as_str()) {
+                referenced.insert(path);
+            } else if let Some(actual_path) = lowercase.get(&path.to_lowercase()) {
+                referenced.insert(actual_path.to_string());
+                report.wrong_case.push(WrongCaseFile {
+                    reference,
+                    actual_path: actual_path.to_string(),
+                });
+            } else {
+                report.missing.push(reference);
+            }
+        }
+
+        report.unreferenced = on_disk
+            .into_iter()
+            .filter(|path| !referenced.contains(path))
+            .collect();
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_files() {
+        let dir = tempdir().unwrap();
+        let modinfo = indoc! {r#"
+            <Mod id="a_mod">
+                <ActionGroups>
+                    <ActionGroup id="game" scope="game">
+                        <Actions>
+                            <UpdateDatabase>
+                                <Item>data/a.sql</Item>
+                                <Item>data/missing.sql</Item>
+                            </UpdateDatabase>
+                            <UIScripts>
+                                <Item>UI/Screen.js</Item>
+                            </UIScripts>
+                        </Actions>
+                    </ActionGroup>
+                </ActionGroups>
+                <LocalizedText>
+                    <File>Text/en_us/ModuleText.xml</File>
+                </LocalizedText>
+            </Mod>
+        "#};
+        for path in [
+            "a_mod.modinfo",
+            "data/a.sql",
+            "ui/screen.js",
+            "text/en_us/ModuleText.xml",
+            "README.md",
+            ".civmods-modinfo.diff",
+            "__MACOSX/data/a.sql",
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        let parsed = Mod::parse(modinfo.as_bytes()).unwrap();
+        let report = parsed.check_files(dir.path()).unwrap();
+
+        let reference = |action_group: Option<&str>, action: &str, path: &str| FileReference {
+            action_group: action_group.map(str::to_string),
+            action: action.to_string(),
+            path: path.to_string(),
+        };
+        assert_eq!(
+            report,
+            FileReport {
+                missing: vec![reference(
+                    Some("game"),
+                    "UpdateDatabase",
+                    "data/missing.sql"
+                )],
+                wrong_case: vec![
+                    WrongCaseFile {
+                        reference: reference(Some("game"), "UIScripts", "UI/Screen.js"),
+                        actual_path: "ui/screen.js".to_string(),
+                    },
+                    WrongCaseFile {
+                        reference: reference(None, "LocalizedText", "Text/en_us/ModuleText.xml"),
+                        actual_path: "text/en_us/ModuleText.xml".to_string(),
+                    },
+                ],
+                unreferenced: vec!["README.md".to_string()],
+            }
+        );
+    }
+}
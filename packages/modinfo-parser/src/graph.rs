@@ -0,0 +1,541 @@
+use crate::Mod;
+use crate::schema::DependencyKind;
+use indexmap::IndexMap;
+use indexmap::map::Entry;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// Modules shipped with the base game, which mods can depend on without them
+/// being installed in the mods folder.
+pub const BUILTIN_MODULES: &[&str] = &[
+    "core",
+    "base-standard",
+    "age-antiquity",
+    "age-exploration",
+    "age-modern",
+];
+
+/// A required dependency that is neither installed nor built-in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MissingDependency {
+    pub mod_id: String,
+    pub dependency_id: String,
+}
+
+/// Everything [`DependencyGraph`] finds, ready to be sent to the desktop app.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyReport {
+    pub order: Vec<String>,
+    pub missing: Vec<MissingDependency>,
+    pub cycles: Vec<Vec<String>>,
+    pub top_level: Vec<String>,
+}
+
+/// Dependencies between a set of mods, e.g. all the mods of the mods folder.
+///
+/// Only `<Dependencies>` constrain the load order: `<References>` are
+/// optional integrations, which are commonly mutual, so they only show up in
+/// the exported graphs.
+#[derive(Debug, Clone)]
+pub struct DependencyGraph<'a> {
+    /// Mods by id. When two mods share an id, the one with the newest
+    /// [`Mod::mod_version`] wins, or else the first one.
+    mods: IndexMap<&'a str, &'a Mod>,
+    builtin: HashSet<String>,
+}
+
+impl<'a> DependencyGraph<'a> {
+    pub fn new(mods: impl IntoIterator<Item = &'a Mod>) -> Self {
+        let mut by_id = IndexMap::new();
+        for modinfo in mods {
+            match by_id.entry(modinfo.id.as_str()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(modinfo);
+                }
+                Entry::Occupied(mut entry) => {
+                    if modinfo.mod_version() > entry.get().mod_version() {
+                        entry.insert(modinfo);
+                    }
+                }
+            }
+        }
+        Self {
+            mods: by_id,
+            builtin: BUILTIN_MODULES.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    /// Adds modules to [`BUILTIN_MODULES`], e.g. those of an installed DLC.
+    pub fn with_builtin(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.builtin.extend(ids.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn is_builtin(&self, id: &str) -> bool {
+        self.builtin.contains(id)
+    }
+
+    /// Ids of the installed mods `id` requires.
+    fn requirements(&self, id: &str) -> impl Iterator<Item = &'a str> + '_ {
+        self.mods
+            .get(id)
+            .into_iter()
+            .flat_map(|modinfo| &modinfo.dependencies)
+            .map(|dependency| dependency.id.as_str())
+            .filter(|id| self.mods.contains_key(id))
+    }
+
+    /// Whether `id` requires `dependency`, directly or through other installed
+    /// mods.
+    pub fn requires(&self, id: &str, dependency: &str) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            for required in self.requirements(current) {
+                if required == dependency {
+                    return true;
+                }
+                if visited.insert(required) {
+                    stack.push(required);
+                }
+            }
+        }
+        false
+    }
+
+    /// Whether one of `a` and `b` requires the other, so that the game loads
+    /// them in a defined order even with the same `LoadOrder`.
+    pub fn is_ordered(&self, a: &str, b: &str) -> bool {
+        self.requires(a, b) || self.requires(b, a)
+    }
+
+    /// Mods in an order where each mod comes after everything it requires.
+    /// Mods caught in a cycle come last, in their original order.
+    pub fn topological_order(&self) -> Vec<&'a str> {
+        let mut order: Vec<&'a str> = vec![];
+        let mut placed = HashSet::new();
+        loop {
+            let next = self.mods.keys().find(|id| {
+                !placed.contains(*id)
+                    && self
+                        .requirements(id)
+                        .all(|dependency| placed.contains(dependency))
+            });
+            match next {
+                Some(id) => {
+                    placed.insert(*id);
+                    order.push(id);
+                }
+                None => break,
+            }
+        }
+        order.extend(self.mods.keys().filter(|id| !placed.contains(*id)));
+        order
+    }
+
+    /// Required dependencies that are neither installed nor built-in.
+    pub fn missing(&self) -> Vec<MissingDependency> {
+        self.mods
+            .values()
+            .flat_map(|modinfo| {
+                modinfo
+                    .dependencies
+                    .iter()
+                    .filter(|dependency| {
+                        !self.mods.contains_key(dependency.id.as_str())
+                            && !self.is_builtin(&dependency.id)
+                    })
+                    .map(|dependency| MissingDependency {
+                        mod_id: modinfo.id.clone(),
+                        dependency_id: dependency.id.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Groups of mods that require each other, directly or not, found with
+    /// Tarjan's algorithm. A mod requiring itself is a cycle of one.
+    pub fn cycles(&self) -> Vec<Vec<&'a str>> {
+        struct Tarjan<'g, 'a> {
+            graph: &'g DependencyGraph<'a>,
+            index: IndexMap<&'a str, (usize, usize)>,
+            stack: Vec<&'a str>,
+            on_stack: HashSet<&'a str>,
+            cycles: Vec<Vec<&'a str>>,
+        }
+
+        impl<'a> Tarjan<'_, 'a> {
+            /// Depth-first search from `root`. The path is kept on the heap so
+            /// that a long chain of dependencies cannot overflow the stack.
+            fn visit(&mut self, root: &'a str) {
+                // Mods on the path, with the requirements left to visit
+                let mut path: Vec<(&'a str, Vec<&'a str>)> = vec![];
+                self.enter(root, &mut path);
+                while let Some((id, dependencies)) = path.last_mut() {
+                    let id = *id;
+                    match dependencies.pop() {
+                        Some(dependency) if !self.index.contains_key(dependency) => {
+                            self.enter(dependency, &mut path);
+                        }
+                        Some(dependency) => {
+                            if self.on_stack.contains(dependency) {
+                                let low = self.index[dependency].0.min(self.index[id].1);
+                                self.index[id].1 = low;
+                            }
+                        }
+                        None => {
+                            path.pop();
+                            self.leave(id);
+                            if let Some((parent, _)) = path.last() {
+                                let low = self.index[id].1.min(self.index[*parent].1);
+                                self.index[*parent].1 = low;
+                            }
+                        }
+                    }
+                }
+            }
+
+            fn enter(&mut self, id: &'a str, path: &mut Vec<(&'a str, Vec<&'a str>)>) {
+                let index = self.index.len();
+                self.index.insert(id, (index, index));
+                self.stack.push(id);
+                self.on_stack.insert(id);
+                let mut dependencies: Vec<&'a str> = self.graph.requirements(id).collect();
+                dependencies.reverse();
+                path.push((id, dependencies));
+            }
+
+            /// Pops the component of `id` once all its requirements are visited.
+            fn leave(&mut self, id: &'a str) {
+                if self.index[id].0 == self.index[id].1 {
+                    let mut component = vec![];
+                    while let Some(member) = self.stack.pop() {
+                        self.on_stack.remove(member);
+                        component.push(member);
+                        if member == id {
+                            break;
+                        }
+                    }
+                    let self_dependency = self.graph.requirements(id).any(|d| d == id);
+                    if component.len() > 1 || self_dependency {
+                        self.cycles.push(component);
+                    }
+                }
+            }
+        }
+
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: IndexMap::new(),
+            stack: vec![],
+            on_stack: HashSet::new(),
+            cycles: vec![],
+        };
+        for id in self.mods.keys() {
+            if !tarjan.index.contains_key(id) {
+                tarjan.visit(id);
+            }
+        }
+
+        let position = |id: &str| self.mods.get_index_of(id);
+        for cycle in &mut tarjan.cycles {
+            cycle.sort_by_key(|id| position(id));
+        }
+        tarjan.cycles.sort_by_key(|cycle| position(cycle[0]));
+        tarjan.cycles
+    }
+
+    /// Installed mods that no other installed mod requires.
+    pub fn top_level(&self) -> Vec<&'a str> {
+        let required: HashSet<&str> = self
+            .mods
+            .keys()
+            .flat_map(|id| {
+                self.requirements(id)
+                    .filter(move |dependency| dependency != id)
+            })
+            .collect();
+        self.mods
+            .keys()
+            .copied()
+            .filter(|id| !required.contains(id))
+            .collect()
+    }
+
+    pub fn report(&self) -> DependencyReport {
+        let owned = |ids: Vec<&str>| ids.into_iter().map(str::to_string).collect();
+        DependencyReport {
+            order: owned(self.topological_order()),
+            missing: self.missing(),
+            cycles: self.cycles().into_iter().map(owned).collect(),
+            top_level: owned(self.top_level()),
+        }
+    }
+
+    /// Every edge as `(mod id, dependency id, kind)`, in input order.
+    fn edges(&self) -> impl Iterator<Item = (&'a str, &'a str, DependencyKind)> + '_ {
+        self.mods.values().flat_map(|modinfo| {
+            modinfo
+                .all_dependencies()
+                .map(|(kind, dependency)| (modinfo.id.as_str(), dependency.id.as_str(), kind))
+        })
+    }
+
+    /// Every node: installed mods first, then the dependencies that are not
+    /// installed, marked as built-in or missing.
+    fn nodes(&self) -> IndexMap<&'a str, NodeKind> {
+        let mut nodes: IndexMap<&str, NodeKind> = self
+            .mods
+            .keys()
+            .map(|id| (*id, NodeKind::Installed))
+            .collect();
+        for (_, dependency, kind) in self.edges() {
+            let node_kind = if self.is_builtin(dependency) {
+                NodeKind::Builtin
+            } else if kind == DependencyKind::Optional {
+                NodeKind::Absent
+            } else {
+                NodeKind::Missing
+            };
+            let entry = nodes.entry(dependency).or_insert(node_kind);
+            if node_kind == NodeKind::Missing && *entry == NodeKind::Absent {
+                *entry = node_kind;
+            }
+        }
+        nodes
+    }
+
+    /// Graphviz export. Edges go from a mod to what it depends on, references
+    /// are dashed, built-in modules are boxes and missing mods are red.
+    pub fn to_dot(&self) -> String {
+        fn quote(id: &str) -> String {
+            format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+
+        let mut dot = String::from("digraph mods {\n");
+        for (id, kind) in self.nodes() {
+            let attributes = match kind {
+                NodeKind::Installed => "",
+                NodeKind::Builtin => " [shape=box]",
+                NodeKind::Missing => " [color=red]",
+                NodeKind::Absent => " [style=dashed]",
+            };
+            let _ = writeln!(dot, "    {}{attributes};", quote(id));
+        }
+        for (id, dependency, kind) in self.edges() {
+            let attributes = match kind {
+                DependencyKind::Required => "",
+                DependencyKind::Optional => " [style=dashed]",
+            };
+            let _ = writeln!(
+                dot,
+                "    {} -> {}{attributes};",
+                quote(id),
+                quote(dependency)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Mermaid flowchart export, styled like [`DependencyGraph::to_dot`].
+    pub fn to_mermaid(&self) -> String {
+        let nodes = self.nodes();
+        let node = |id: &str| format!("n{}", nodes.get_index_of(id).unwrap_or_default());
+
+        let mut mermaid = String::from("graph TD\n");
+        for (index, (id, kind)) in nodes.iter().enumerate() {
+            let label = id.replace('"', "#quot;");
+            let _ = match kind {
+                NodeKind::Builtin => writeln!(mermaid, "    n{index}[\"{label}\"]"),
+                _ => writeln!(mermaid, "    n{index}([\"{label}\"])"),
+            };
+        }
+        for (id, dependency, kind) in self.edges() {
+            let arrow = match kind {
+                DependencyKind::Required => "-->",
+                DependencyKind::Optional => "-.->",
+            };
+            let _ = writeln!(mermaid, "    {} {arrow} {}", node(id), node(dependency));
+        }
+        for (class, node_kind, style) in [
+            ("missing", NodeKind::Missing, "stroke:#f00,color:#f00"),
+            ("absent", NodeKind::Absent, "stroke-dasharray:5 5"),
+        ] {
+            let ids: Vec<String> = nodes
+                .iter()
+                .filter(|(_, kind)| **kind == node_kind)
+                .map(|(id, _)| node(id))
+                .collect();
+            if !ids.is_empty() {
+                let _ = writeln!(mermaid, "    classDef {class} {style}");
+                let _ = writeln!(mermaid, "    class {} {class}", ids.join(","));
+            }
+        }
+        mermaid
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Installed,
+    Builtin,
+    /// Not installed, but required.
+    Missing,
+    /// Not installed, only referenced.
+    Absent,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn mods() -> Vec<Mod> {
+        [
+            ("ui", vec!["lib", "base-standard"], vec!["extra"]),
+            ("lib", vec!["core"], vec![]),
+            ("patch", vec!["ui", "gone"], vec![]),
+            ("a", vec!["b"], vec![]),
+            ("b", vec!["a"], vec![]),
+        ]
+        .into_iter()
+        .map(|(id, dependencies, references)| {
+            let builder = dependencies
+                .into_iter()
+                .fold(Mod::builder(id), |builder, dependency| {
+                    builder.dependency(dependency, None)
+                });
+            references
+                .into_iter()
+                .fold(builder, |builder, reference| {
+                    builder.reference(reference, None)
+                })
+                .build()
+                .unwrap()
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_dependency_report() {
+        let mods = mods();
+        let graph = DependencyGraph::new(&mods);
+        assert_eq!(
+            graph.report(),
+            DependencyReport {
+                order: vec!["lib", "ui", "patch", "a", "b"]
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+                missing: vec![MissingDependency {
+                    mod_id: "patch".to_string(),
+                    dependency_id: "gone".to_string(),
+                }],
+                cycles: vec![vec!["a".to_string(), "b".to_string()]],
+                top_level: vec!["patch".to_string()],
+            }
+        );
+
+        let graph = DependencyGraph::new(&mods).with_builtin(["gone"]);
+        assert_eq!(graph.missing(), vec![]);
+    }
+
+    #[test]
+    fn test_dependency_graph_duplicate_ids() {
+        let mods = [
+            Mod::builder("lib").version("1.0-beta10").build().unwrap(),
+            Mod::builder("lib")
+                .version("1.0-beta2")
+                .dependency("old", None)
+                .build()
+                .unwrap(),
+            Mod::builder("lib")
+                .version("1.0 beta10")
+                .dependency("same", None)
+                .build()
+                .unwrap(),
+        ];
+        let graph = DependencyGraph::new(&mods);
+        assert_eq!(graph.mods["lib"].version.as_deref(), Some("1.0-beta10"));
+        assert_eq!(graph.missing(), vec![]);
+
+        // A newer copy wins even when it comes last
+        let graph = DependencyGraph::new(&mods[1..]);
+        assert_eq!(graph.mods["lib"].version.as_deref(), Some("1.0 beta10"));
+        assert_eq!(
+            graph.missing(),
+            [MissingDependency {
+                mod_id: "lib".to_string(),
+                dependency_id: "same".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_dependency_cycles_long_chain() {
+        let count = 100_000;
+        let mods: Vec<Mod> = (0..count)
+            .map(|i| {
+                Mod::builder(format!("mod_{i}"))
+                    .dependency(format!("mod_{}", (i + 1) % count), None)
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        let cycles = DependencyGraph::new(&mods).cycles();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].len(), count);
+        assert_eq!(cycles[0][0], "mod_0");
+    }
+
+    #[test]
+    fn test_dependency_exports() {
+        let mods = &mods()[..3];
+        let graph = DependencyGraph::new(mods);
+        assert_eq!(
+            graph.to_dot(),
+            indoc! {r#"
+                digraph mods {
+                    "ui";
+                    "lib";
+                    "patch";
+                    "base-standard" [shape=box];
+                    "extra" [style=dashed];
+                    "core" [shape=box];
+                    "gone" [color=red];
+                    "ui" -> "lib";
+                    "ui" -> "base-standard";
+                    "ui" -> "extra" [style=dashed];
+                    "lib" -> "core";
+                    "patch" -> "ui";
+                    "patch" -> "gone";
+                }
+            "#}
+        );
+        assert_eq!(
+            graph.to_mermaid(),
+            indoc! {r#"
+                graph TD
+                    n0(["ui"])
+                    n1(["lib"])
+                    n2(["patch"])
+                    n3["base-standard"]
+                    n4(["extra"])
+                    n5["core"]
+                    n6(["gone"])
+                    n0 --> n1
+                    n0 --> n3
+                    n0 -.-> n4
+                    n1 --> n5
+                    n2 --> n0
+                    n2 --> n6
+                    classDef missing stroke:#f00,color:#f00
+                    class n6 missing
+                    classDef absent stroke-dasharray:5 5
+                    class n4 absent
+            "#}
+        );
+    }
+}
@@ -1,54 +1,158 @@
-use quick_xml::errors::{Error, IllFormedError};
-use quick_xml::events::{BytesEnd, Event};
-use quick_xml::{Reader, Writer};
+use serde::Deserialize;
 use std::fs::File;
-use std::io::{BufReader, Read, Write};
+use std::io::Read;
 use std::path::Path;
 
+pub mod builder;
+pub mod compatibility;
+pub mod conflicts;
+pub mod criteria;
+pub mod database;
+pub mod diff;
+pub mod edit;
+pub mod encoding;
+pub mod error;
+pub mod files;
+pub mod graph;
+pub mod load_order;
+pub mod sanitize;
 pub mod schema;
+pub mod text;
+pub mod validate;
+pub mod version;
+pub mod write;
+pub use builder::{ActionGroupBuilder, BuildError, ModBuilder};
+pub use compatibility::GameVersionRange;
+pub use conflicts::{FileConflict, detect_file_conflicts};
+pub use criteria::GameContext;
+pub use database::{DatabaseConflict, detect_database_conflicts};
+pub use diff::{ModDiff, diff};
+pub use edit::{Document, EditError};
+pub use encoding::DetectedEncoding;
+pub use error::ModinfoError;
+pub use graph::{DependencyGraph, DependencyReport};
+pub use load_order::{LoadPlan, simulate_load_order};
+pub use sanitize::{Repair, Sanitizer, sanitize};
 pub use schema::Mod;
+pub use text::{LocalizedStrings, TextConflict, detect_text_conflicts};
+pub use validate::{Diagnostic, DiagnosticKind, Severity};
+pub use version::ModVersion;
+pub use write::{Indent, WriteOptions};
+
+/// Everything learned while parsing a modinfo file.
+#[derive(Debug)]
+pub struct ParsedMod {
+    pub modinfo: Mod,
+    /// Repairs applied by the sanitizer.
+    pub repairs: Vec<Repair>,
+    pub encoding: DetectedEncoding,
+}
 
 impl Mod {
-    pub fn open(modinfo_path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        Ok(Mod::parse(File::open(modinfo_path)?)?)
+    pub fn open(modinfo_path: impl AsRef<Path>) -> Result<Self, ModinfoError> {
+        Mod::parse(File::open(modinfo_path)?)
+    }
+
+    /// Like [`Mod::open`], also returning the repairs applied by the sanitizer.
+    pub fn open_with_repairs(
+        modinfo_path: impl AsRef<Path>,
+    ) -> Result<(Self, Vec<Repair>), ModinfoError> {
+        Mod::parse_with_repairs(File::open(modinfo_path)?)
+    }
+
+    /// Like [`Mod::open`], also returning the repairs and the encoding.
+    pub fn open_detailed(modinfo_path: impl AsRef<Path>) -> Result<ParsedMod, ModinfoError> {
+        Mod::parse_detailed(File::open(modinfo_path)?)
     }
 
-    pub fn parse(source: impl Read) -> Result<Self, quick_xml::DeError> {
-        let mut buffer = vec![];
-        Self::sanitize(source, &mut buffer)?;
-        let sanitized = String::from_utf8_lossy(&buffer).to_string();
-        quick_xml::de::from_str(&sanitized)
+    pub fn parse(source: impl Read) -> Result<Self, ModinfoError> {
+        Ok(Self::parse_detailed(source)?.modinfo)
+    }
+
+    /// Like [`Mod::parse`], also returning the repairs applied by the sanitizer.
+    pub fn parse_with_repairs(source: impl Read) -> Result<(Self, Vec<Repair>), ModinfoError> {
+        let parsed = Self::parse_detailed(source)?;
+        Ok((parsed.modinfo, parsed.repairs))
+    }
+
+    /// Like [`Mod::parse`], also returning the repairs and the encoding. The
+    /// source is decoded to UTF-8 first, so repair positions refer to the
+    /// decoded text.
+    pub fn parse_detailed(source: impl Read) -> Result<ParsedMod, ModinfoError> {
+        let (sanitized, repairs, encoding) = read_sanitized(source)?;
+        Ok(ParsedMod {
+            modinfo: quick_xml::de::from_str(&sanitized)?,
+            repairs,
+            encoding,
+        })
     }
 
     pub fn to_string(&self) -> Result<String, quick_xml::SeError> {
         quick_xml::se::to_string(self)
     }
+}
+
+/// Decodes and sanitizes a modinfo source, ready to be deserialized.
+fn read_sanitized(
+    mut source: impl Read,
+) -> Result<(String, Vec<Repair>, DetectedEncoding), ModinfoError> {
+    let mut bytes = vec![];
+    source.read_to_end(&mut bytes)?;
+    let (text, encoding, mut repairs) = encoding::decode(&bytes)?;
+
+    let mut buffer = vec![];
+    repairs.extend(sanitize(text.as_bytes(), &mut buffer)?);
+    // The sanitizer only ever writes UTF-8 when given UTF-8
+    let sanitized = String::from_utf8(buffer).expect("sanitized modinfo is UTF-8");
+    Ok((sanitized, repairs, encoding))
+}
+
+/// What identifies an installed mod: its id and the properties injected by
+/// CivMods. Read leniently, so that a mod stays identified when its modinfo
+/// doesn't match the full [`Mod`] schema.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ModIdentity {
+    pub id: Option<String>,
+    pub civmods_internal_version_id: Option<String>,
+}
+
+impl ModIdentity {
+    pub fn open(modinfo_path: impl AsRef<Path>) -> Result<Self, ModinfoError> {
+        Self::parse(File::open(modinfo_path)?)
+    }
 
-    fn sanitize(reader: impl Read, writer: impl Write) -> quick_xml::Result<()> {
-        let mut reader = Reader::from_reader(BufReader::new(reader));
-        let mut writer = Writer::new(writer);
-
-        let mut buffer = Vec::new();
-        loop {
-            let event = match reader.read_event_into(&mut buffer) {
-                Ok(Event::Eof) => return Ok(()),
-                Ok(event) => event,
-                Err(Error::IllFormed(IllFormedError::MismatchedEndTag { expected, found })) => {
-                    log::warn!("Mismatched end tag: expected: {expected:?}, found: {found:?}");
-                    Event::End(BytesEnd::new(expected))
-                }
-                Err(Error::IllFormed(IllFormedError::MissingEndTag(tag))) => {
-                    log::warn!("Missing end tag: {tag:?}");
-                    Event::End(BytesEnd::new(tag))
-                }
-                Err(Error::IllFormed(IllFormedError::UnmatchedEndTag(tag))) => {
-                    log::warn!("Unmatched end tag: {tag:?}");
-                    Event::End(BytesEnd::new(tag))
-                }
-                Err(err) => return Err(err),
-            };
-            writer.write_event(event)?;
-            buffer.clear();
+    /// Reads only `<Mod id>` and `<CivModsInternalVersionId>`, ignoring
+    /// everything else.
+    pub fn parse(source: impl Read) -> Result<Self, ModinfoError> {
+        #[derive(Deserialize)]
+        struct IdentityXml {
+            #[serde(rename = "@id")]
+            id: Option<String>,
+            #[serde(rename = "Properties")]
+            #[serde(default)]
+            properties: IdentityProperties,
+        }
+
+        #[derive(Default, Deserialize)]
+        struct IdentityProperties {
+            #[serde(rename = "CivModsInternalVersionId")]
+            civmods_internal_version_id: Option<String>,
+        }
+
+        let (sanitized, _, _) = read_sanitized(source)?;
+        let xml: IdentityXml = quick_xml::de::from_str(&sanitized)?;
+        Ok(Self {
+            id: xml.id,
+            civmods_internal_version_id: xml.properties.civmods_internal_version_id,
+        })
+    }
+}
+
+impl From<&Mod> for ModIdentity {
+    fn from(modinfo: &Mod) -> Self {
+        Self {
+            id: Some(modinfo.id.clone()),
+            civmods_internal_version_id: modinfo.properties.civmods_internal_version_id.clone(),
         }
     }
 }
@@ -56,15 +160,90 @@ impl Mod {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_parse_malformed_mod() {
+        // NOTE: This is not a valid ModInfo XML
+        let xml = r#"<Mod id="a_mod"><Item>ui/shell/extras/screen-extras.js</item></Mod>"#;
+        let (parsed, repairs) = Mod::parse_with_repairs(xml.as_bytes()).unwrap();
+        assert_eq!(parsed.id, "a_mod");
+        assert_eq!(repairs.len(), 1);
+        assert_eq!(repairs[0].original, "</item>");
+    }
+
+    #[test]
+    fn test_parse_recovered_mod() {
+        let xml = concat!(
+            "\u{feff}junk\u{feff}<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+            "<Mod id=a_mod version=\"1\" version=\"2\">",
+            "<Properties><Description>Units cost < 50% gold</Description></Properties>",
+            "</Mod>\n</Mod>",
+        );
+        let (parsed, repairs) = Mod::parse_with_repairs(xml.as_bytes()).unwrap();
+        assert_eq!(parsed.id, "a_mod");
+        assert_eq!(parsed.version.as_deref(), Some("1"));
+        assert_eq!(
+            parsed.properties.description.as_deref(),
+            Some("Units cost < 50% gold")
+        );
+        assert_eq!(repairs.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = Mod::open("does/not/exist.modinfo").unwrap_err();
+        assert!(matches!(error, ModinfoError::Io(_)));
+
+        let error = Mod::parse(r#"<Mod version="1"></Mod>"#.as_bytes()).unwrap_err();
+        assert!(matches!(error, ModinfoError::Schema { .. }));
+        assert_eq!(error.to_string(), "Invalid modinfo: missing field `@id`");
+
+        let error =
+            Mod::parse("<Mod id=\"a_mod\">\n  <!-- comment -- ->\n</Mod>".as_bytes()).unwrap_err();
+        assert!(matches!(error, ModinfoError::Xml { .. }));
+        assert_eq!(error.position().map(|position| position.line), Some(2));
+    }
+
+    #[test]
+    fn test_parse_identity_of_invalid_mod() {
+        let xml = indoc! {r#"
+            <Mod id="a_mod">
+                <Properties>
+                    <CivModsInternalVersionId>42</CivModsInternalVersionId>
+                    <Extra><Nested>value</Nested></Extra>
+                </Properties>
+                <ActionGroups><ActionGroup scope="game"/></ActionGroups>
+            </Mod>
+        "#};
+        assert!(matches!(
+            Mod::parse(xml.as_bytes()),
+            Err(ModinfoError::Schema { .. })
+        ));
+        let identity = ModIdentity::parse(xml.as_bytes()).unwrap();
+        assert_eq!(identity.id.as_deref(), Some("a_mod"));
+        assert_eq!(identity.civmods_internal_version_id.as_deref(), Some("42"));
+    }
+
+    #[test]
+    fn test_parse_legacy_encoding() {
+        let xml = b"<Mod id=\"a_mod\"><Properties><Authors>Andr\xE9</Authors></Properties></Mod>";
+        let parsed = Mod::parse_detailed(&xml[..]).unwrap();
+        assert_eq!(
+            parsed.modinfo.properties.authors.as_deref(),
+            Some("Andr\u{e9}")
+        );
+        assert_eq!(parsed.encoding.name(), "windows-1252");
+    }
 
     #[test]
-    fn test_sanitize_xml() {
-        let xml = r#"<Item>ui/shell/extras/screen-extras.js</item>"#;
-        let mut output = vec![];
-        Mod::sanitize(xml.as_bytes(), &mut output).unwrap();
+    fn test_parse_ampersand_in_text() {
+        let xml =
+            r#"<Mod id="a_mod"><Properties><Name>Mod by me & friends</Name></Properties></Mod>"#;
+        let parsed = Mod::parse(xml.as_bytes()).unwrap();
         assert_eq!(
-            String::from_utf8_lossy(&output),
-            r#"<Item>ui/shell/extras/screen-extras.js</Item>"#,
+            parsed.properties.name.as_deref(),
+            Some("Mod by me & friends")
         );
     }
 }
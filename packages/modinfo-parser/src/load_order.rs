@@ -0,0 +1,280 @@
+use crate::Mod;
+use crate::criteria::GameContext;
+use crate::graph::DependencyGraph;
+use crate::schema::ActionGroup;
+use serde::Serialize;
+
+/// A file the game applies, in [`LoadPlan::files`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LoadStep {
+    pub mod_id: String,
+    pub action_group: String,
+    pub load_order: i64,
+    /// The action element, e.g. `UpdateDatabase`.
+    pub action: String,
+    pub path: String,
+    pub locale: Option<String>,
+}
+
+/// Mods whose action groups share a `LoadOrder`: the game doesn't define
+/// which of them goes first. Two mods are only tied when neither requires the
+/// other, as dependencies load first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LoadOrderTie {
+    pub load_order: i64,
+    pub mod_ids: Vec<String>,
+}
+
+/// An action group whose criteria the [`GameContext`] cannot settle, left out
+/// of [`LoadPlan::files`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UndeterminedGroup {
+    pub mod_id: String,
+    pub action_group: String,
+}
+
+/// What the game loads for one scope, see [`simulate_load_order`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LoadPlan {
+    pub scope: String,
+    pub age: Option<String>,
+    pub files: Vec<LoadStep>,
+    pub ties: Vec<LoadOrderTie>,
+    pub undetermined: Vec<UndeterminedGroup>,
+}
+
+impl LoadPlan {
+    /// Whether `step` belongs to a tie, i.e. may actually load before or after
+    /// its neighbours with the same `LoadOrder`.
+    pub fn is_ambiguous(&self, step: &LoadStep) -> bool {
+        self.ties
+            .iter()
+            .any(|tie| tie.load_order == step.load_order && tie.mod_ids.contains(&step.mod_id))
+    }
+}
+
+/// `LoadOrder` of the group, 0 when unset.
+pub(crate) fn load_order(group: &ActionGroup) -> i64 {
+    group.properties.load_order.unwrap_or_default()
+}
+
+/// Every action group of `mods`, whatever their scope and criteria, sorted by
+/// `LoadOrder`, then by dependency order, then by the order of `mods`.
+///
+/// Each mod comes with its `data`, e.g. the folder of its modinfo, which keeps
+/// apart two copies of a mod installed in different folders.
+pub(crate) fn by_load_order<'a, T: Copy>(
+    mods: impl IntoIterator<Item = (&'a Mod, T)>,
+) -> Vec<(&'a Mod, T, &'a ActionGroup)> {
+    let mods: Vec<(&Mod, T)> = mods.into_iter().collect();
+    let graph = DependencyGraph::new(mods.iter().map(|(modinfo, _)| *modinfo));
+    let mut groups: Vec<(&Mod, T, &ActionGroup)> = graph
+        .topological_order()
+        .into_iter()
+        .flat_map(|id| mods.iter().filter(move |(modinfo, _)| modinfo.id == id))
+        .flat_map(|&(modinfo, data)| {
+            modinfo
+                .action_groups
+                .iter()
+                .map(move |group| (modinfo, data, group))
+        })
+        .collect();
+    groups.sort_by_key(|(_, _, group)| load_order(group));
+    groups
+}
+
+/// Lists the files the game applies for `scope` (`game` or `shell`) in
+/// `context`, across all `mods`.
+///
+/// Active action groups are applied by ascending `LoadOrder`, 0 when unset.
+/// Ties are broken by dependency order, then by the order of `mods`, which is
+/// this crate's best guess and is reported in [`LoadPlan::ties`].
+pub fn simulate_load_order<'a>(
+    mods: impl IntoIterator<Item = &'a Mod>,
+    scope: &str,
+    context: &GameContext,
+) -> LoadPlan {
+    let mods: Vec<&Mod> = mods.into_iter().collect();
+    let graph = DependencyGraph::new(mods.iter().copied());
+    let mut undetermined = vec![];
+    let mut groups: Vec<(&Mod, &ActionGroup)> = vec![];
+    for (modinfo, (), group) in by_load_order(mods.iter().map(|modinfo| (*modinfo, ()))) {
+        if group.scope.as_deref() != Some(scope) {
+            continue;
+        }
+        match modinfo.evaluate_action_group(group, context) {
+            Some(true) => groups.push((modinfo, group)),
+            Some(false) => {}
+            None => undetermined.push(UndeterminedGroup {
+                mod_id: modinfo.id.clone(),
+                action_group: group.id.clone(),
+            }),
+        }
+    }
+
+    let mut ties: Vec<LoadOrderTie> = vec![];
+    for same_load_order in groups.chunk_by(|(_, a), (_, b)| load_order(a) == load_order(b)) {
+        let mut mod_ids: Vec<&str> = vec![];
+        for (modinfo, _) in same_load_order {
+            if !mod_ids.contains(&modinfo.id.as_str()) {
+                mod_ids.push(&modinfo.id);
+            }
+        }
+        let tied: Vec<String> = mod_ids
+            .iter()
+            .filter(|id| {
+                mod_ids
+                    .iter()
+                    .any(|other| other != *id && !graph.is_ordered(id, other))
+            })
+            .map(|id| id.to_string())
+            .collect();
+        if !tied.is_empty() {
+            ties.push(LoadOrderTie {
+                load_order: load_order(same_load_order[0].1),
+                mod_ids: tied,
+            });
+        }
+    }
+
+    let files = groups
+        .iter()
+        .flat_map(|(modinfo, group)| {
+            group
+                .actions
+                .iter()
+                .flat_map(|actions| actions.iter())
+                .flat_map(move |action| {
+                    action.items().map(move |item| LoadStep {
+                        mod_id: modinfo.id.clone(),
+                        action_group: group.id.clone(),
+                        load_order: load_order(group),
+                        action: action.name.clone(),
+                        path: item.path.clone(),
+                        locale: item.locale.clone(),
+                    })
+                })
+        })
+        .collect();
+
+    LoadPlan {
+        scope: scope.to_string(),
+        age: context.age.clone(),
+        files,
+        ties,
+        undetermined,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{ActionKind, CriterionCondition};
+
+    #[test]
+    fn test_simulate_load_order() -> anyhow::Result<()> {
+        let patch = Mod::builder("patch")
+            .dependency("base", None)
+            .action_group(
+                ActionGroup::builder("patch-game", "game")
+                    .load_order(10)
+                    .action(ActionKind::UpdateDatabase, "patch.sql"),
+            )
+            .build()?;
+        let base = Mod::builder("base")
+            .criteria(
+                "exploration",
+                [CriterionCondition::AgeInUse("AGE_EXPLORATION".into())],
+            )
+            .criteria(
+                "leader",
+                [CriterionCondition::LeaderPlayable("LEADER_X".into())],
+            )
+            .action_group(
+                ActionGroup::builder("base-game", "game")
+                    .load_order(10)
+                    .action(ActionKind::UpdateDatabase, "base.sql")
+                    .action(ActionKind::ImportFiles, "ui/base.js"),
+            )
+            .action_group(
+                ActionGroup::builder("base-exploration", "game")
+                    .criteria("exploration")
+                    .action(ActionKind::UpdateDatabase, "exploration.sql"),
+            )
+            .action_group(
+                ActionGroup::builder("base-leader", "game")
+                    .criteria("leader")
+                    .action(ActionKind::UpdateDatabase, "leader.sql"),
+            )
+            .action_group(
+                ActionGroup::builder("base-shell", "shell")
+                    .action(ActionKind::UIScripts, "ui/shell.js"),
+            )
+            .build()?;
+        let other = Mod::builder("other")
+            .action_group(
+                ActionGroup::builder("other-game", "game")
+                    .load_order(-1)
+                    .action(ActionKind::UpdateText, "text.xml"),
+            )
+            .build()?;
+
+        let plan = simulate_load_order(
+            [&patch, &base, &other],
+            "game",
+            &GameContext::in_age("AGE_EXPLORATION"),
+        );
+        let files: Vec<(&str, &str, i64)> = plan
+            .files
+            .iter()
+            .map(|step| (step.mod_id.as_str(), step.path.as_str(), step.load_order))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                ("other", "text.xml", -1),
+                ("base", "exploration.sql", 0),
+                ("base", "base.sql", 10),
+                ("base", "ui/base.js", 10),
+                ("patch", "patch.sql", 10),
+            ]
+        );
+        // `patch` requires `base`, so it loads after it
+        assert_eq!(plan.ties, []);
+        assert!(!plan.is_ambiguous(&plan.files[4]));
+        assert_eq!(
+            plan.undetermined,
+            [UndeterminedGroup {
+                mod_id: "base".to_string(),
+                action_group: "base-leader".to_string(),
+            }]
+        );
+
+        let extra = Mod::builder("extra")
+            .action_group(
+                ActionGroup::builder("extra-game", "game")
+                    .load_order(10)
+                    .action(ActionKind::UpdateDatabase, "extra.sql"),
+            )
+            .build()?;
+        let plan = simulate_load_order(
+            [&patch, &base, &other, &extra],
+            "game",
+            &GameContext::in_age("AGE_EXPLORATION"),
+        );
+        assert_eq!(
+            plan.ties,
+            [LoadOrderTie {
+                load_order: 10,
+                mod_ids: vec!["base".to_string(), "patch".to_string(), "extra".to_string()],
+            }]
+        );
+        assert!(plan.is_ambiguous(&plan.files[4]));
+        assert!(!plan.is_ambiguous(&plan.files[0]));
+
+        let plan = simulate_load_order([&patch, &base, &other], "shell", &GameContext::default());
+        assert_eq!(plan.files.len(), 1);
+        assert_eq!(plan.files[0].action, "UIScripts");
+        Ok(())
+    }
+}
@@ -0,0 +1,574 @@
+use crate::error::ModinfoError;
+use quick_xml::errors::{Error, IllFormedError};
+use quick_xml::escape;
+use quick_xml::events::attributes::AttrError;
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::io::{Read, Write};
+use std::ops::Range;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// A location in the source, before any repair. Modinfo files are decoded to
+/// UTF-8 first (see [`crate::encoding::decode`]), so positions refer to the
+/// decoded text, which is the file itself for UTF-8 files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TextPosition {
+    /// Byte offset from the start of the UTF-8 source.
+    pub offset: usize,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column, counted in characters.
+    pub column: usize,
+}
+
+impl TextPosition {
+    pub(crate) fn locate(source: &[u8], offset: usize) -> Self {
+        let offset = offset.min(source.len());
+        let before = &source[..offset];
+        let line_start = before
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |i| i + 1);
+        Self {
+            offset,
+            line: before.iter().filter(|b| **b == b'\n').count() + 1,
+            column: String::from_utf8_lossy(&before[line_start..])
+                .chars()
+                .count()
+                + 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum RepairKind {
+    MismatchedEndTag,
+    MissingEndTag,
+    UnmatchedEndTag,
+    UnescapedText,
+    /// A `<` in text that does not start a tag, e.g. `Costs < 5 gold`.
+    UnescapedLessThan,
+    DuplicateAttribute,
+    UnquotedAttributeValue,
+    MalformedAttribute,
+    /// Anything but whitespace or a byte order mark before the first tag.
+    LeadingContent,
+    /// Anything but whitespace, comments or processing instructions after
+    /// the root element.
+    TrailingContent,
+    /// Bytes that are not valid UTF-8 in a UTF-8 file, replaced with U+FFFD.
+    InvalidByteSequence,
+}
+
+/// A fix applied by the [`Sanitizer`], with enough context to tell a mod
+/// author what is wrong with their file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Repair {
+    pub kind: RepairKind,
+    pub position: TextPosition,
+    /// The offending source, e.g. `</item>`.
+    pub original: String,
+    /// What was written in its place, e.g. `</Item>`.
+    pub replacement: String,
+}
+
+/// Event reader that repairs the most common mistakes found in community
+/// modinfo files (mismatched, missing or unmatched end tags and bare `&` in
+/// text nodes) instead of failing. Every fix is recorded in [`Sanitizer::repairs`].
+///
+/// In recovery mode, which is on by default, it also repairs stray `<` in
+/// text, duplicate or unquoted attributes, and drops content before the
+/// first tag or after the root element.
+pub struct Sanitizer<'a> {
+    source: &'a [u8],
+    reader: Reader<&'a [u8]>,
+    /// Offset of the reader input in `source`.
+    base: usize,
+    /// Names of the currently open elements.
+    open: Vec<String>,
+    pending: VecDeque<Event<'a>>,
+    /// Where the last event came from in `source`.
+    span: Range<usize>,
+    recovery: bool,
+    started: bool,
+    root_closed: bool,
+    finished: bool,
+    repairs: Vec<Repair>,
+}
+
+fn reader_at(source: &[u8], offset: usize) -> Reader<&[u8]> {
+    let mut reader = Reader::from_reader(&source[offset..]);
+    // End tags are checked by the sanitizer, so it can repair them
+    reader.config_mut().check_end_names = false;
+    reader
+}
+
+/// Whether `name` can start an XML element name. quick-xml happily reads
+/// `< b>` or `<=` as tags, these are text that should have been escaped.
+fn is_element_name(name: &[u8]) -> bool {
+    name.first()
+        .is_some_and(|b| b.is_ascii_alphabetic() || *b == b'_' || *b == b':' || *b >= 0x80)
+}
+
+impl<'a> Sanitizer<'a> {
+    pub fn new(source: &'a [u8]) -> Self {
+        Self {
+            source,
+            reader: reader_at(source, 0),
+            base: 0,
+            open: vec![],
+            pending: VecDeque::new(),
+            span: 0..0,
+            recovery: true,
+            started: false,
+            root_closed: false,
+            finished: false,
+            repairs: vec![],
+        }
+    }
+
+    /// Enables or disables the recovery of stray `<`, broken attributes and
+    /// content outside of the root element.
+    pub fn with_recovery(mut self, recovery: bool) -> Self {
+        self.recovery = recovery;
+        self
+    }
+
+    /// Repairs applied so far.
+    pub fn repairs(&self) -> &[Repair] {
+        &self.repairs
+    }
+
+    pub fn into_repairs(self) -> Vec<Repair> {
+        self.repairs
+    }
+
+    fn repair(&mut self, kind: RepairKind, start: usize, end: usize, replacement: String) {
+        let end = end.clamp(start, self.source.len());
+        let start = start.min(end);
+        let repair = Repair {
+            kind,
+            position: TextPosition::locate(self.source, start),
+            original: String::from_utf8_lossy(&self.source[start..end]).to_string(),
+            replacement,
+        };
+        log::warn!(
+            "{:?} at {}:{}: {:?} -> {:?}",
+            repair.kind,
+            repair.position.line,
+            repair.position.column,
+            repair.original,
+            repair.replacement
+        );
+        self.repairs.push(repair);
+    }
+
+    /// Byte range in the source of the last event returned by
+    /// [`Sanitizer::next_event`]. Missing end tags are added at the end of the
+    /// source.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Where the last error returned by [`Sanitizer::next_event`] occurred.
+    pub fn error_position(&self) -> TextPosition {
+        TextPosition::locate(
+            self.source,
+            self.base + self.reader.error_position() as usize,
+        )
+    }
+
+    fn position(&self) -> usize {
+        self.base + self.reader.buffer_position() as usize
+    }
+
+    /// Restarts reading at `offset` in the source.
+    fn seek(&mut self, offset: usize) {
+        self.reader = reader_at(self.source, offset);
+        self.base = offset;
+    }
+
+    /// Reads the next event, repairing it when possible. Returns [`Event::Eof`]
+    /// once the input is exhausted.
+    pub fn next_event(&mut self) -> quick_xml::Result<Event<'a>> {
+        loop {
+            if let Some(event) = self.read_event()? {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Reads and repairs one event, or returns `None` when it was dropped or
+    /// replaced by pending events.
+    fn read_event(&mut self) -> quick_xml::Result<Option<Event<'a>>> {
+        if let Some(event) = self.pending.pop_front() {
+            self.span = self.source.len()..self.source.len();
+            return Ok(Some(event));
+        }
+        if self.finished {
+            return Ok(Some(Event::Eof));
+        }
+        if !self.started {
+            self.started = true;
+            if self.recovery {
+                self.skip_leading_content();
+            }
+        }
+
+        let start = self.position();
+        let event = match self.reader.read_event() {
+            // Raised even without `check_end_names` when no element is open
+            Err(Error::IllFormed(IllFormedError::UnmatchedEndTag(name))) => {
+                Event::End(BytesEnd::new(name))
+            }
+            event => event?,
+        };
+        let end = self.position();
+        self.span = start..end;
+
+        if self.recovery && self.root_closed && is_content(&event) {
+            self.repair(
+                RepairKind::TrailingContent,
+                start,
+                self.source.len(),
+                String::new(),
+            );
+            self.finished = true;
+            return Ok(Some(Event::Eof));
+        }
+
+        let event = match event {
+            Event::Start(tag) | Event::Empty(tag)
+                if self.recovery && !is_element_name(tag.name().as_ref()) =>
+            {
+                // Only the `<` is wrong, read again from the next character
+                self.repair(
+                    RepairKind::UnescapedLessThan,
+                    start,
+                    start + 1,
+                    "&lt;".into(),
+                );
+                self.seek(start + 1);
+                self.span = start..start + 1;
+                Event::Text(BytesText::from_escaped("&lt;"))
+            }
+            Event::Start(tag) => {
+                let tag = self.repair_attributes(tag, start, end, ">");
+                self.open
+                    .push(String::from_utf8_lossy(tag.name().as_ref()).to_string());
+                Event::Start(tag)
+            }
+            Event::Empty(tag) => {
+                if self.open.is_empty() {
+                    self.root_closed = true;
+                }
+                Event::Empty(self.repair_attributes(tag, start, end, "/>"))
+            }
+            Event::End(tag) => {
+                let name = String::from_utf8_lossy(tag.name().as_ref()).to_string();
+                let event = match self.open.pop() {
+                    Some(expected) if expected == name => Event::End(tag),
+                    Some(expected) => {
+                        self.repair(
+                            RepairKind::MismatchedEndTag,
+                            start,
+                            end,
+                            format!("</{expected}>"),
+                        );
+                        Event::End(BytesEnd::new(expected))
+                    }
+                    None => {
+                        self.repair(RepairKind::UnmatchedEndTag, start, end, String::new());
+                        return Ok(None);
+                    }
+                };
+                if self.open.is_empty() {
+                    self.root_closed = true;
+                }
+                event
+            }
+            Event::Text(text) => {
+                // If we fail to unescape, it means it's not a valid XML text node
+                // and we should escape it instead.
+                if text.unescape().is_err() {
+                    let raw = String::from_utf8_lossy(&text).to_string();
+                    let escaped = escape::partial_escape(&raw).into_owned();
+                    self.repair(RepairKind::UnescapedText, start, end, escaped.clone());
+                    Event::Text(BytesText::from_escaped(escaped))
+                } else {
+                    Event::Text(text)
+                }
+            }
+            Event::Eof if !self.open.is_empty() => {
+                let missing: Vec<String> = self.open.drain(..).rev().collect();
+                let replacement = missing.iter().map(|tag| format!("</{tag}>")).collect();
+                self.repair(RepairKind::MissingEndTag, end, end, replacement);
+                self.pending.extend(
+                    missing
+                        .into_iter()
+                        .map(|tag| Event::End(BytesEnd::new(tag))),
+                );
+                self.finished = true;
+                return Ok(None);
+            }
+            event => event,
+        };
+        Ok(Some(event))
+    }
+
+    fn skip_leading_content(&mut self) {
+        let Some(first_tag) = self.source.iter().position(|b| *b == b'<') else {
+            return;
+        };
+        let prefix = &self.source[..first_tag];
+        // quick-xml skips a byte order mark on its own
+        let prefix = prefix.strip_prefix(UTF8_BOM).unwrap_or(prefix);
+        if prefix.iter().all(u8::is_ascii_whitespace) {
+            return;
+        }
+        self.repair(RepairKind::LeadingContent, 0, first_tag, String::new());
+        self.seek(first_tag);
+    }
+
+    /// Rewrites `tag` without duplicate attributes (the first one wins) and
+    /// with every value quoted, if needed. `end_of_tag` is `>` or `/>`.
+    fn repair_attributes(
+        &mut self,
+        tag: BytesStart<'a>,
+        start: usize,
+        end: usize,
+        end_of_tag: &str,
+    ) -> BytesStart<'a> {
+        if !self.recovery {
+            return tag;
+        }
+        let Some(error) = tag.attributes().find_map(Result::err) else {
+            return tag;
+        };
+        let kind = match error {
+            AttrError::Duplicated(..) => RepairKind::DuplicateAttribute,
+            AttrError::UnquotedValue(_) => RepairKind::UnquotedAttributeValue,
+            _ => RepairKind::MalformedAttribute,
+        };
+
+        let mut repaired =
+            BytesStart::new(String::from_utf8_lossy(tag.name().as_ref()).to_string());
+        let mut seen = HashSet::new();
+        let mut attributes = tag.html_attributes();
+        attributes.with_checks(false);
+        for attribute in attributes.flatten() {
+            if !seen.insert(attribute.key.as_ref().to_vec()) {
+                continue;
+            }
+            let value = String::from_utf8_lossy(&attribute.value).replace('"', "&quot;");
+            repaired.push_attribute((attribute.key.as_ref(), value.as_bytes()));
+        }
+
+        let replacement = format!("<{}{end_of_tag}", String::from_utf8_lossy(&repaired));
+        self.repair(kind, start, end, replacement);
+        repaired
+    }
+}
+
+/// Whether `event` is part of the document content, as opposed to whitespace,
+/// comments and processing instructions allowed after the root element.
+fn is_content(event: &Event) -> bool {
+    match event {
+        Event::Text(text) => !text.iter().all(u8::is_ascii_whitespace),
+        Event::Start(_) | Event::Empty(_) | Event::End(_) | Event::CData(_) => true,
+        _ => false,
+    }
+}
+
+/// Copies `reader` into `writer`, repairing the XML along the way. Returns the
+/// repairs that were applied.
+pub fn sanitize(mut reader: impl Read, writer: impl Write) -> Result<Vec<Repair>, ModinfoError> {
+    let mut source = vec![];
+    reader.read_to_end(&mut source)?;
+
+    let mut sanitizer = Sanitizer::new(&source);
+    let mut writer = Writer::new(writer);
+
+    loop {
+        let event = sanitizer.next_event().map_err(|error| {
+            let offset = sanitizer.error_position().offset;
+            ModinfoError::from_xml(error, &source, Some(offset))
+        })?;
+        match event {
+            Event::Eof => return Ok(sanitizer.into_repairs()),
+            event => writer.write_event(event)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sanitize_str(xml: &str) -> (String, Vec<Repair>) {
+        let mut output = vec![];
+        let repairs = sanitize(xml.as_bytes(), &mut output).unwrap();
+        (String::from_utf8(output).unwrap(), repairs)
+    }
+
+    #[test]
+    fn test_sanitize_xml() {
+        assert_eq!(
+            sanitize_str(r#"<Item>ui/shell/extras/screen-extras.js</item>"#).0,
+            r#"<Item>ui/shell/extras/screen-extras.js</Item>"#,
+        );
+    }
+
+    #[test]
+    fn test_sanitize_ampersand_in_text() {
+        assert_eq!(
+            sanitize_str(
+                r#"<Mod id="a_mod"><Properties><Name>Mod by me & friends</Name></Properties></Mod>"#
+            )
+            .0,
+            r#"<Mod id="a_mod"><Properties><Name>Mod by me &amp; friends</Name></Properties></Mod>"#,
+        );
+    }
+
+    #[test]
+    fn test_sanitize_keeps_valid_text() {
+        let xml = r#"<Name>Tom &amp; Jerry's "mod"</Name>"#;
+        assert_eq!(sanitize_str(xml), (xml.to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_sanitize_missing_and_unmatched_end_tags() {
+        let (output, repairs) = sanitize_str("</Extra><Mod><Name>A</name><Items>");
+        assert_eq!(output, "<Mod><Name>A</Name><Items></Items></Mod>");
+        let kinds: Vec<_> = repairs.iter().map(|repair| repair.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                RepairKind::UnmatchedEndTag,
+                RepairKind::MismatchedEndTag,
+                RepairKind::MissingEndTag
+            ]
+        );
+        assert_eq!(repairs[2].replacement, "</Items></Mod>");
+
+        // Dropped end tags don't grow the stack
+        let (output, repairs) = sanitize_str(&format!("{}<Mod/>", "</Extra>".repeat(1_000)));
+        assert_eq!(output, "<Mod/>");
+        assert_eq!(repairs.len(), 1_000);
+    }
+
+    #[test]
+    fn test_sanitize_unescaped_less_than() {
+        let (output, repairs) =
+            sanitize_str("<Description>Costs < 5 gold, <3 turns, a<=b</Description>");
+        assert_eq!(
+            output,
+            "<Description>Costs &lt; 5 gold, &lt;3 turns, a&lt;=b</Description>"
+        );
+        assert_eq!(repairs.len(), 3);
+        assert!(
+            repairs
+                .iter()
+                .all(|repair| repair.kind == RepairKind::UnescapedLessThan)
+        );
+        assert_eq!(repairs[0].position.offset, 19);
+    }
+
+    #[test]
+    fn test_sanitize_attributes() {
+        let (output, repairs) =
+            sanitize_str(r#"<Mod id="a_mod" version=1 id="b_mod"><A x='1' y=2/></Mod>"#);
+        assert_eq!(
+            output,
+            r#"<Mod id="a_mod" version="1"><A x="1" y="2"/></Mod>"#
+        );
+        assert_eq!(
+            repairs,
+            vec![
+                Repair {
+                    kind: RepairKind::UnquotedAttributeValue,
+                    position: TextPosition {
+                        offset: 0,
+                        line: 1,
+                        column: 1,
+                    },
+                    original: r#"<Mod id="a_mod" version=1 id="b_mod">"#.to_string(),
+                    replacement: r#"<Mod id="a_mod" version="1">"#.to_string(),
+                },
+                Repair {
+                    kind: RepairKind::UnquotedAttributeValue,
+                    position: TextPosition {
+                        offset: 37,
+                        line: 1,
+                        column: 38,
+                    },
+                    original: "<A x='1' y=2/>".to_string(),
+                    replacement: r#"<A x="1" y="2"/>"#.to_string(),
+                },
+            ]
+        );
+
+        let (output, repairs) = sanitize_str(r#"<Mod id="a" id="b"/>"#);
+        assert_eq!(output, r#"<Mod id="a"/>"#);
+        assert_eq!(repairs[0].kind, RepairKind::DuplicateAttribute);
+    }
+
+    #[test]
+    fn test_sanitize_content_outside_root() {
+        let xml =
+            "\u{feff}garbage\u{feff}<?xml version=\"1.0\"?>\n<Mod/>\n<!-- done -->\n</Mod>junk";
+        let (output, repairs) = sanitize_str(xml);
+        assert_eq!(output, "<?xml version=\"1.0\"?>\n<Mod/>\n<!-- done -->\n");
+        let kinds: Vec<_> = repairs.iter().map(|repair| repair.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![RepairKind::LeadingContent, RepairKind::TrailingContent]
+        );
+        assert_eq!(repairs[1].original, "</Mod>junk");
+
+        // A lone byte order mark is fine
+        let xml = "\u{feff}<?xml version=\"1.0\"?><Mod/>";
+        assert_eq!(sanitize_str(xml).1, vec![]);
+    }
+
+    #[test]
+    fn test_sanitize_without_recovery() {
+        let xml = "junk<Mod a=1 a=2/>";
+        let mut sanitizer = Sanitizer::new(xml.as_bytes()).with_recovery(false);
+        while sanitizer.next_event().unwrap() != Event::Eof {}
+        assert_eq!(sanitizer.repairs(), &[]);
+    }
+
+    #[test]
+    fn test_sanitize_repairs() {
+        let (_, repairs) = sanitize_str("<Mod>\n  <Item>ü & ä</item>\n</Mod>");
+        assert_eq!(
+            repairs,
+            vec![
+                Repair {
+                    kind: RepairKind::UnescapedText,
+                    position: TextPosition {
+                        offset: 14,
+                        line: 2,
+                        column: 9,
+                    },
+                    original: "ü & ä".to_string(),
+                    replacement: "ü &amp; ä".to_string(),
+                },
+                Repair {
+                    kind: RepairKind::MismatchedEndTag,
+                    position: TextPosition {
+                        offset: 21,
+                        line: 2,
+                        column: 14,
+                    },
+                    original: "</item>".to_string(),
+                    replacement: "</Item>".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -1,18 +1,29 @@
+use crate::builder::BuildError;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, skip_serializing_none};
+use std::borrow::Cow;
+use std::collections::HashMap;
 
-serde_with::serde_conv!(
-    MarkerTag,
-    bool,
-    |value: &bool| if *value { vec![()] } else { vec![] },
-    |option: Vec<()>| -> Result<_, std::convert::Infallible> { Ok(!option.is_empty()) }
-);
+/// Flags are written both as `1`/`0` and as `true`/`false`.
+fn read_flag(value: &str) -> Option<bool> {
+    match value.trim() {
+        "1" | "true" | "True" | "TRUE" => Some(true),
+        "0" | "false" | "False" | "FALSE" => Some(false),
+        _ => None,
+    }
+}
+
+/// Attribute flags are off unless set.
+fn parse_flag(value: &str) -> bool {
+    read_flag(value).unwrap_or(false)
+}
 
 serde_with::serde_conv!(
-    OneHotEncoding,
+    Flag,
     bool,
     |value: &bool| if *value { 1 } else { 0 },
-    |num: u8| -> Result<_, std::convert::Infallible> { Ok(num > 0) }
+    |text: String| -> Result<_, std::convert::Infallible> { Ok(parse_flag(&text)) }
 );
 
 macro_rules! gen_list_parser {
@@ -54,10 +65,6 @@ macro_rules! gen_list_parser {
     };
 }
 
-const fn default_true() -> bool {
-    true
-}
-
 #[serde_as]
 #[skip_serializing_none]
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -68,15 +75,24 @@ pub struct Mod {
     #[serde(rename = "@version")]
     pub version: Option<String>,
 
+    /// `ModInfo` in the game's own files.
+    #[serde(rename = "@xmlns")]
+    pub xmlns: Option<String>,
+
     #[serde(rename = "Properties")]
     #[serde(default)]
     pub properties: Properties,
 
     #[serde(rename = "Dependencies")]
-    #[serde(with = "parse_dependencies")]
+    #[serde(with = "parse_dependency_list")]
     #[serde(default)]
     pub dependencies: Vec<Dependency>,
 
+    #[serde(rename = "References")]
+    #[serde(with = "parse_dependency_list")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<Dependency>,
+
     #[serde(rename = "LocalizedText")]
     #[serde(with = "parse_localized_text")]
     #[serde(default)]
@@ -93,47 +109,270 @@ pub struct Mod {
     pub action_groups: Vec<ActionGroup>,
 }
 
-gen_list_parser!(parse_dependencies, "Mod", Dependency);
+// `<Dependencies>` and `<References>` only differ by their `DependencyKind`.
+gen_list_parser!(parse_dependency_list, "Mod", Dependency);
 gen_list_parser!(parse_localized_text, "File", String);
 gen_list_parser!(parse_action_criteria, "Criteria", ActionCriterion);
 gen_list_parser!(parse_action_groups, "ActionGroup", ActionGroup);
 
 /// Some properties are defined at [https://github.com/thecrazyscotsman/TCS-Improved-Mod-Page?tab=readme-ov-file#custom-properties].
-#[serde_as]
-#[skip_serializing_none]
-#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+///
+/// Properties without a dedicated field, such as TCS's `ShowInBrowser` or the
+/// ones injected by CivMods, are kept in `extra`, or in `elements` when they
+/// have child elements, duplicates included. `order` keeps the document order
+/// of all of them; properties missing from it are written after, in field order.
+#[derive(Debug, Default, PartialEq)]
 pub struct Properties {
-    #[serde(rename = "Name")]
     pub name: Option<String>,
-
-    #[serde(rename = "Description")]
     pub description: Option<String>,
-
-    #[serde(rename = "Authors")]
     pub authors: Option<String>,
-
-    #[serde(rename = "Package")]
     pub package: Option<String>,
-
-    #[serde(rename = "AffectsSavedGames")]
-    #[serde_as(as = "OneHotEncoding")]
-    #[serde(default = "default_true")]
     pub affects_saved_games: bool,
-
-    #[serde(rename = "SpecialThanks")]
     pub special_thanks: Option<String>,
-
-    #[serde(rename = "Version")]
     pub version: Option<String>,
-
-    #[serde(rename = "Compatibility")]
     pub compatibility: Option<String>,
-
-    #[serde(rename = "URL")]
     pub url: Option<String>,
-
-    #[serde(rename = "CivModsInternalVersionId")]
     pub civmods_internal_version_id: Option<String>,
+    pub extra: Vec<(String, String)>,
+    pub elements: Vec<(String, RawElement)>,
+    /// Element names in document order, repeated for duplicates, so that
+    /// writing the properties back keeps it. Properties missing from it are
+    /// written last, in field order.
+    pub order: Vec<String>,
+}
+
+/// The value of a property, as written in `<Properties>`.
+enum PropertyValue<'a> {
+    Text(Cow<'a, str>),
+    Element(&'a RawElement),
+}
+
+impl Serialize for PropertyValue<'_> {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            PropertyValue::Text(text) => text.serialize(serializer),
+            PropertyValue::Element(element) => element.serialize(serializer),
+        }
+    }
+}
+
+impl Properties {
+    fn fields(&self) -> [(&'static str, Option<Cow<'_, str>>); 10] {
+        fn text(value: &Option<String>) -> Option<Cow<'_, str>> {
+            value.as_deref().map(Cow::Borrowed)
+        }
+        let affects_saved_games = if self.affects_saved_games { "1" } else { "0" };
+        [
+            ("Name", text(&self.name)),
+            ("Description", text(&self.description)),
+            ("Authors", text(&self.authors)),
+            ("Package", text(&self.package)),
+            (
+                "AffectsSavedGames",
+                Some(Cow::Borrowed(affects_saved_games)),
+            ),
+            ("SpecialThanks", text(&self.special_thanks)),
+            ("Version", text(&self.version)),
+            ("Compatibility", text(&self.compatibility)),
+            ("URL", text(&self.url)),
+            (
+                "CivModsInternalVersionId",
+                text(&self.civmods_internal_version_id),
+            ),
+        ]
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut Option<String>> {
+        match name {
+            "Name" => Some(&mut self.name),
+            "Description" => Some(&mut self.description),
+            "Authors" => Some(&mut self.authors),
+            "Package" => Some(&mut self.package),
+            "SpecialThanks" => Some(&mut self.special_thanks),
+            "Version" => Some(&mut self.version),
+            "Compatibility" => Some(&mut self.compatibility),
+            "URL" => Some(&mut self.url),
+            "CivModsInternalVersionId" => Some(&mut self.civmods_internal_version_id),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw value of the property named `name`, as written in the modinfo.
+    pub fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Sets the property named `name`, using the dedicated field when there is one.
+    /// Among duplicate properties, only the first one is changed.
+    pub fn set(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<(), BuildError> {
+        let name = name.into();
+        let value = value.into();
+        if name == "AffectsSavedGames" {
+            self.affects_saved_games = match read_flag(&value) {
+                Some(flag) => flag,
+                None => match value.trim().parse::<u8>() {
+                    Ok(flag) => flag > 0,
+                    Err(_) => return Err(BuildError::InvalidProperty { name, value }),
+                },
+            };
+        } else if let Some(field) = self.field_mut(&name) {
+            *field = Some(value);
+        } else if let Some((_, existing)) = self.extra.iter_mut().find(|(key, _)| *key == name) {
+            *existing = value;
+        } else {
+            self.extra.push((name, value));
+        }
+        Ok(())
+    }
+
+    /// All properties that are set, in document order.
+    fn entries(&self) -> Vec<(&str, PropertyValue<'_>)> {
+        let entries: Vec<(&str, PropertyValue)> =
+            self.fields()
+                .into_iter()
+                .filter_map(|(name, value)| Some((name, PropertyValue::Text(value?))))
+                .chain(self.extra.iter().map(|(name, value)| {
+                    (name.as_str(), PropertyValue::Text(Cow::Borrowed(value)))
+                }))
+                .chain(
+                    self.elements
+                        .iter()
+                        .map(|(name, element)| (name.as_str(), PropertyValue::Element(element))),
+                )
+                .collect();
+        // The n-th property named `name` goes where the n-th such element was.
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        let positions: Vec<usize> = entries
+            .iter()
+            .map(|(name, _)| {
+                let nth = seen.entry(name).or_default();
+                let position = self
+                    .order
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, ordered)| ordered == name)
+                    .nth(*nth)
+                    .map(|(position, _)| position);
+                *nth += 1;
+                position.unwrap_or(self.order.len())
+            })
+            .collect();
+        let mut entries: Vec<_> = positions.into_iter().zip(entries).collect();
+        entries.sort_by_key(|(position, _)| *position);
+        entries.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    /// Iterates over all properties that are set, as `(element name, raw value)`,
+    /// in document order. Properties with child elements are left out.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Cow<'_, str>)> {
+        self.entries()
+            .into_iter()
+            .filter_map(|(name, value)| match value {
+                PropertyValue::Text(text) => Some((name, text)),
+                PropertyValue::Element(_) => None,
+            })
+    }
+
+    /// Reads back the properties injected by CivMods when it installs a mod.
+    pub fn civmods(&self) -> Option<CivModsProperties> {
+        let extra = |name: &str| {
+            let entry = self.extra.iter().find(|(key, _)| key == name);
+            entry.map(|(_, value)| value.clone())
+        };
+        Some(CivModsProperties {
+            internal_version_id: self.civmods_internal_version_id.clone()?,
+            mod_url: extra("CivModsURL")?,
+            mod_version: extra("CivModsVersion"),
+            mod_category: extra("CivModsCategory"),
+            mod_version_date: extra("CivModsVersionDate"),
+        })
+    }
+}
+
+impl Serialize for Properties {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ::serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        for (name, value) in self.entries() {
+            map.serialize_entry(name, &value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Properties {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use ::serde::de::{Error, IgnoredAny, MapAccess, Visitor};
+
+        struct PropertiesVisitor;
+
+        impl<'de> Visitor<'de> for PropertiesVisitor {
+            type Value = Properties;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a list of properties")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut properties = Properties {
+                    affects_saved_games: true,
+                    ..Default::default()
+                };
+                while let Some(name) = map.next_key::<String>()? {
+                    // Stray text or attributes on `<Properties>`
+                    if name.starts_with(['$', '@']) {
+                        map.next_value::<IgnoredAny>()?;
+                        continue;
+                    }
+                    let element = map.next_value::<RawElement>()?;
+                    let is_known =
+                        name == "AffectsSavedGames" || properties.field_mut(&name).is_some();
+                    properties.order.push(name.clone());
+                    if is_known {
+                        properties
+                            .set(name, element.text.unwrap_or_default())
+                            .map_err(|err| A::Error::custom(err.to_string()))?;
+                    } else if element.attributes.is_empty() && element.children.is_empty() {
+                        properties
+                            .extra
+                            .push((name, element.text.unwrap_or_default()));
+                    } else {
+                        properties.elements.push((name, element));
+                    }
+                }
+                // `AffectsSavedGames` is always written back, after the other properties
+                // when the document doesn't have it.
+                if !properties
+                    .order
+                    .iter()
+                    .any(|name| name == "AffectsSavedGames")
+                {
+                    properties.order.push("AffectsSavedGames".to_string());
+                }
+                Ok(properties)
+            }
+        }
+
+        deserializer.deserialize_map(PropertiesVisitor)
+    }
+}
+
+/// Metadata injected into `<Properties>` by the CivMods desktop app, see
+/// `CivModsProperties` in `apps/desktop/src-tauri/src/mods/patch_modinfo.rs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CivModsProperties {
+    pub internal_version_id: String,
+    pub mod_url: String,
+    pub mod_version: Option<String>,
+    pub mod_category: Option<String>,
+    pub mod_version_date: Option<String>,
 }
 
 #[skip_serializing_none]
@@ -146,36 +385,232 @@ pub struct Dependency {
     pub title: Option<String>,
 }
 
-#[serde_as]
-#[skip_serializing_none]
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// Tells a hard dependency apart from a soft reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum DependencyKind {
+    /// Listed under `<Dependencies>`: the mod doesn't work without it.
+    Required,
+    /// Listed under `<References>`: an optional integration, used when present.
+    Optional,
+}
+
+impl Mod {
+    /// Iterates over both `<Dependencies>` and `<References>`.
+    pub fn all_dependencies(&self) -> impl Iterator<Item = (DependencyKind, &Dependency)> {
+        [DependencyKind::Required, DependencyKind::Optional]
+            .into_iter()
+            .flat_map(|kind| {
+                let dependencies = self.dependencies_of(kind).iter();
+                dependencies.map(move |dependency| (kind, dependency))
+            })
+    }
+
+    /// `<Dependencies>` or `<References>`, depending on `kind`.
+    pub fn dependencies_of(&self, kind: DependencyKind) -> &Vec<Dependency> {
+        match kind {
+            DependencyKind::Required => &self.dependencies,
+            DependencyKind::Optional => &self.references,
+        }
+    }
+}
+
+/// A `<Criteria>` element. The criterion is met when all of its conditions
+/// are met, or any of them when `any="true"`.
+#[derive(Debug, PartialEq)]
 pub struct ActionCriterion {
-    #[serde(rename = "@id")]
     pub id: String,
+    pub any: bool,
+    pub conditions: Vec<CriterionCondition>,
+}
 
-    #[serde(rename = "AlwaysMet")]
-    #[serde_as(as = "MarkerTag")]
-    #[serde(default)]
-    pub always_met: bool,
+macro_rules! gen_criterion_conditions {
+    (
+        markers { $($marker:ident,)* }
+        values { $($value:ident,)* }
+        configurations { $($configuration:ident,)* }
+    ) => {
+        /// A single test inside a `<Criteria>` element, named after its element.
+        #[derive(Debug, PartialEq)]
+        pub enum CriterionCondition {
+            $($marker,)*
+            $($value(ConditionValue),)*
+            $($configuration(ConfigurationCondition),)*
+            /// A condition unknown to this crate, kept with its attributes and
+            /// child elements so that a round-trip does not lose it.
+            Unknown {
+                name: String,
+                element: RawElement,
+            },
+        }
+
+        impl CriterionCondition {
+            /// Returns the element name of the condition.
+            pub fn name(&self) -> &str {
+                match self {
+                    $(Self::$marker => stringify!($marker),)*
+                    $(Self::$value(_) => stringify!($value),)*
+                    $(Self::$configuration(_) => stringify!($configuration),)*
+                    Self::Unknown { name, .. } => name,
+                }
+            }
+
+            fn serialize_entry<M: ::serde::ser::SerializeMap>(
+                &self,
+                map: &mut M,
+            ) -> Result<(), M::Error> {
+                match self {
+                    $(Self::$marker => map.serialize_entry(stringify!($marker), &()),)*
+                    $(Self::$value(value) => map.serialize_entry(stringify!($value), value),)*
+                    $(Self::$configuration(value) => {
+                        map.serialize_entry(stringify!($configuration), value)
+                    })*
+                    Self::Unknown { name, element } => map.serialize_entry(name, element),
+                }
+            }
+
+            fn deserialize_value<'de, M: ::serde::de::MapAccess<'de>>(
+                name: String,
+                map: &mut M,
+            ) -> Result<Self, M::Error> {
+                Ok(match name.as_str() {
+                    $(stringify!($marker) => {
+                        map.next_value::<::serde::de::IgnoredAny>()?;
+                        Self::$marker
+                    })*
+                    $(stringify!($value) => Self::$value(map.next_value()?),)*
+                    $(stringify!($configuration) => Self::$configuration(map.next_value()?),)*
+                    _ => Self::Unknown {
+                        name,
+                        element: map.next_value()?,
+                    },
+                })
+            }
+        }
+    };
+}
 
-    #[serde(rename = "AgeInUse")]
-    pub age_in_use: Option<String>,
+gen_criterion_conditions! {
+    markers {
+        AlwaysMet,
+        NeverMet,
+    }
+    values {
+        AgeInUse,
+        AgeWasUsed,
+        AgeEverInUse,
+        ModInUse,
+        RuleSetInUse,
+        LeaderPlayable,
+        CivilizationPlayable,
+    }
+    configurations {
+        ConfigurationValueMatches,
+        ConfigurationValueContains,
+    }
+}
+
+impl Serialize for ActionCriterion {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ::serde::ser::SerializeMap;
 
-    #[serde(rename = "ModInUse")]
-    pub mod_in_use: Option<ActionCriterionModInUse>,
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("@id", &self.id)?;
+        if self.any {
+            map.serialize_entry("@any", "true")?;
+        }
+        for condition in &self.conditions {
+            condition.serialize_entry(&mut map)?;
+        }
+        map.end()
+    }
 }
 
+impl<'de> Deserialize<'de> for ActionCriterion {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use ::serde::de::{Error, IgnoredAny, MapAccess, Visitor};
+
+        struct CriterionVisitor;
+
+        impl<'de> Visitor<'de> for CriterionVisitor {
+            type Value = ActionCriterion;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a criteria element")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut id = None;
+                let mut any = false;
+                let mut conditions = vec![];
+                while let Some(name) = map.next_key::<String>()? {
+                    match name.as_str() {
+                        "@id" => id = Some(map.next_value::<String>()?),
+                        "@any" => any = parse_flag(&map.next_value::<String>()?),
+                        // Stray text or other attributes on `<Criteria>`
+                        _ if name.starts_with(['$', '@']) => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                        _ => {
+                            conditions.push(CriterionCondition::deserialize_value(name, &mut map)?)
+                        }
+                    }
+                }
+                Ok(ActionCriterion {
+                    id: id.ok_or_else(|| A::Error::missing_field("@id"))?,
+                    any,
+                    conditions,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(CriterionVisitor)
+    }
+}
+
+/// The text value of a condition such as `<AgeInUse>AGE_ANTIQUITY</AgeInUse>`.
 #[serde_as]
-#[skip_serializing_none]
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
-pub struct ActionCriterionModInUse {
-    #[serde(rename = "$value")]
-    pub mod_id: String,
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConditionValue {
+    #[serde(rename = "$text")]
+    #[serde(default)]
+    pub value: String,
 
     #[serde(rename = "@inverse")]
-    #[serde_as(as = "OneHotEncoding")]
-    #[serde(default)]
+    #[serde_as(as = "Flag")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inverse: bool,
+}
+
+impl From<&str> for ConditionValue {
+    fn from(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            inverse: false,
+        }
+    }
+}
+
+/// A test on a game setup parameter, e.g.
+/// `<ConfigurationValueMatches><Group>Game</Group><ConfigurationId>...</ConfigurationId><Value>...</Value></ConfigurationValueMatches>`.
+#[serde_as]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConfigurationCondition {
+    #[serde(rename = "@inverse")]
+    #[serde_as(as = "Flag")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub inverse: bool,
+
+    #[serde(rename = "Group")]
+    #[serde(default)]
+    pub group: String,
+
+    #[serde(rename = "ConfigurationId")]
+    #[serde(default)]
+    pub configuration_id: String,
+
+    #[serde(rename = "Value")]
+    #[serde(default)]
+    pub value: String,
 }
 
 #[skip_serializing_none]
@@ -203,31 +638,351 @@ pub struct ActionGroup {
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct ActionGroupProperties {
     #[serde(rename = "LoadOrder")]
-    pub load_order: Option<i32>,
+    pub load_order: Option<i64>,
 }
 
-#[skip_serializing_none]
-#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+macro_rules! gen_action_catalog {
+    ($($(#[$meta:meta])* $action:ident,)*) => {
+        /// Action elements known to this crate, named after their element.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum ActionKind {
+            $($(#[$meta])* $action,)*
+        }
+
+        impl ActionKind {
+            pub const ALL: &[ActionKind] = &[$(ActionKind::$action,)*];
+
+            /// Element name, e.g. `UpdateDatabase`.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(ActionKind::$action => stringify!($action),)*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($action) => Some(ActionKind::$action),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+gen_action_catalog! {
+    UpdateDatabase,
+    UpdateText,
+    UpdateIcons,
+    UpdateColors,
+    UpdateArt,
+    UpdateVisualRemaps,
+    ImportFiles,
+    UIScripts,
+    UIShortcuts,
+    ScenarioScripts,
+    Scripts,
+}
+
+/// An element this crate has no model for, kept as is so that a round-trip
+/// never loses it. Its name is kept by its parent.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RawElement {
+    pub attributes: IndexMap<String, String>,
+    pub text: Option<String>,
+    pub children: Vec<(String, RawElement)>,
+}
+
+impl RawElement {
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty() && self.text.is_none() && self.children.is_empty()
+    }
+
+    /// Reads the next entry of the element, `@name` being an attribute and
+    /// `$text` its text.
+    fn read_entry<'de, M: ::serde::de::MapAccess<'de>>(
+        &mut self,
+        name: String,
+        map: &mut M,
+    ) -> Result<(), M::Error> {
+        if let Some(attribute) = name.strip_prefix('@') {
+            self.attributes
+                .insert(attribute.to_string(), map.next_value()?);
+        } else if name.starts_with('$') {
+            let text = map.next_value::<String>()?;
+            if !text.trim().is_empty() {
+                self.text = Some(text);
+            }
+        } else {
+            self.children.push((name, map.next_value()?));
+        }
+        Ok(())
+    }
+
+    fn serialize_attributes<M: ::serde::ser::SerializeMap>(
+        &self,
+        map: &mut M,
+    ) -> Result<(), M::Error> {
+        for (name, value) in &self.attributes {
+            map.serialize_entry(&format!("@{name}"), value)?;
+        }
+        Ok(())
+    }
+
+    fn serialize_content<M: ::serde::ser::SerializeMap>(
+        &self,
+        map: &mut M,
+    ) -> Result<(), M::Error> {
+        if let Some(text) = &self.text {
+            map.serialize_entry("$text", text)?;
+        }
+        for (name, child) in &self.children {
+            map.serialize_entry(name, child)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for RawElement {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ::serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        self.serialize_attributes(&mut map)?;
+        self.serialize_content(&mut map)?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for RawElement {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use ::serde::de::{MapAccess, Visitor};
+
+        struct RawElementVisitor;
+
+        impl<'de> Visitor<'de> for RawElementVisitor {
+            type Value = RawElement;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an element")
+            }
+
+            fn visit_str<E: ::serde::de::Error>(self, text: &str) -> Result<Self::Value, E> {
+                Ok(RawElement {
+                    text: (!text.trim().is_empty()).then(|| text.to_string()),
+                    ..Default::default()
+                })
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut element = RawElement::default();
+                while let Some(name) = map.next_key::<String>()? {
+                    element.read_entry(name, &mut map)?;
+                }
+                Ok(element)
+            }
+        }
+
+        deserializer.deserialize_map(RawElementVisitor)
+    }
+}
+
+/// An element inside `<Actions>`, e.g. `<UpdateDatabase>`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Action {
+    /// Element name, which may be unknown to this crate, e.g. an action added
+    /// by a newer game version.
+    pub name: String,
+    pub attributes: IndexMap<String, String>,
+    pub text: Option<String>,
+    /// `<Item>`s and any other child elements, in document order.
+    pub children: Vec<ActionChild>,
+}
+
+/// A child element of an action.
+#[derive(Debug, PartialEq)]
+pub enum ActionChild {
+    Item(ActionItem),
+    /// Any element other than `<Item>`, e.g. options of a newer game version.
+    Element(String, RawElement),
+}
+
+impl Action {
+    pub fn new(kind: ActionKind) -> Self {
+        Self {
+            name: kind.name().to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// `None` for an action unknown to this crate.
+    pub fn kind(&self) -> Option<ActionKind> {
+        ActionKind::from_name(&self.name)
+    }
+
+    /// The `<Item>` children, in document order.
+    pub fn items(&self) -> impl Iterator<Item = &ActionItem> {
+        self.children.iter().filter_map(|child| match child {
+            ActionChild::Item(item) => Some(item),
+            ActionChild::Element(..) => None,
+        })
+    }
+}
+
+/// The content of an action, its name being the key in `<Actions>`.
+impl Serialize for Action {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ::serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        for (name, value) in &self.attributes {
+            map.serialize_entry(&format!("@{name}"), value)?;
+        }
+        if let Some(text) = &self.text {
+            map.serialize_entry("$text", text)?;
+        }
+        for child in &self.children {
+            match child {
+                ActionChild::Item(item) => map.serialize_entry("Item", item)?,
+                ActionChild::Element(name, element) => map.serialize_entry(name, element)?,
+            }
+        }
+        map.end()
+    }
+}
+
+/// Every action element inside `<Actions>`, in document order. Elements and
+/// attributes this crate doesn't know about are kept, so that a round-trip
+/// never loses them.
+#[derive(Debug, Default, PartialEq)]
 pub struct ActionGroupActions {
-    #[serde(rename = "ImportFiles")]
-    #[serde(with = "parse_action_items")]
-    #[serde(default)]
-    pub import_files: Vec<ActionItem>,
+    pub actions: Vec<Action>,
+}
 
-    #[serde(rename = "UIScripts")]
-    #[serde(with = "parse_action_items")]
-    #[serde(default)]
-    pub ui_scripts: Vec<ActionItem>,
+impl ActionGroupActions {
+    /// Items of every `kind` element, in document order.
+    pub fn items(&self, kind: ActionKind) -> impl Iterator<Item = &ActionItem> {
+        self.actions
+            .iter()
+            .filter(move |action| action.kind() == Some(kind))
+            .flat_map(|action| action.items())
+    }
 
-    #[serde(rename = "UpdateDatabase")]
-    #[serde(with = "parse_action_items")]
-    #[serde(default)]
-    pub update_database: Vec<ActionItem>,
+    /// Adds `item` to the last `kind` element, added when there is none.
+    pub fn push_item(&mut self, kind: ActionKind, item: ActionItem) {
+        let index = match self
+            .actions
+            .iter()
+            .rposition(|action| action.kind() == Some(kind))
+        {
+            Some(index) => index,
+            None => {
+                self.actions.push(Action::new(kind));
+                self.actions.len() - 1
+            }
+        };
+        self.actions[index].children.push(ActionChild::Item(item));
+    }
 
-    #[serde(rename = "UpdateText")]
-    #[serde(with = "parse_action_items")]
-    #[serde(default)]
-    pub update_text: Vec<ActionItem>,
+    /// Iterates over all actions with at least one item, in document order.
+    pub fn iter(&self) -> impl Iterator<Item = &Action> {
+        self.actions
+            .iter()
+            .filter(|action| action.items().next().is_some())
+    }
+}
+
+impl Serialize for ActionGroupActions {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ::serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        for action in &self.actions {
+            map.serialize_entry(&action.name, action)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ActionGroupActions {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use ::serde::de::{IgnoredAny, MapAccess, Visitor};
+
+        /// An action element, read in two steps as its name is the key.
+        struct ActionContent(Action);
+
+        impl<'de> Deserialize<'de> for ActionContent {
+            fn deserialize<D: ::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                struct ActionVisitor;
+
+                impl<'de> Visitor<'de> for ActionVisitor {
+                    type Value = ActionContent;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str("an action")
+                    }
+
+                    fn visit_str<E: ::serde::de::Error>(self, _: &str) -> Result<Self::Value, E> {
+                        Ok(ActionContent(Action::default()))
+                    }
+
+                    fn visit_map<A: MapAccess<'de>>(
+                        self,
+                        mut map: A,
+                    ) -> Result<Self::Value, A::Error> {
+                        let mut action = Action::default();
+                        while let Some(name) = map.next_key::<String>()? {
+                            if name == "Item" {
+                                action.children.push(ActionChild::Item(map.next_value()?));
+                            } else if let Some(attribute) = name.strip_prefix('@') {
+                                action
+                                    .attributes
+                                    .insert(attribute.to_string(), map.next_value()?);
+                            } else if name.starts_with('$') {
+                                let text = map.next_value::<String>()?;
+                                if !text.trim().is_empty() {
+                                    action.text = Some(text);
+                                }
+                            } else {
+                                let element = map.next_value()?;
+                                action.children.push(ActionChild::Element(name, element));
+                            }
+                        }
+                        Ok(ActionContent(action))
+                    }
+                }
+
+                deserializer.deserialize_map(ActionVisitor)
+            }
+        }
+
+        struct ActionsVisitor;
+
+        impl<'de> Visitor<'de> for ActionsVisitor {
+            type Value = ActionGroupActions;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a list of actions")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut actions = ActionGroupActions::default();
+                while let Some(name) = map.next_key::<String>()? {
+                    // Stray text or attributes on `<Actions>`
+                    if name.starts_with(['$', '@']) {
+                        map.next_value::<IgnoredAny>()?;
+                        continue;
+                    }
+                    let ActionContent(action) = map.next_value()?;
+                    actions.actions.push(Action { name, ..action });
+                }
+                Ok(actions)
+            }
+        }
+
+        deserializer.deserialize_map(ActionsVisitor)
+    }
 }
 
 #[serde_as]
@@ -235,14 +990,13 @@ pub struct ActionGroupActions {
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ActionItem {
     #[serde(rename = "$value")]
+    #[serde(default)]
     pub path: String,
 
     #[serde(rename = "@locale")]
     pub locale: Option<String>,
 }
 
-gen_list_parser!(parse_action_items, "Item", ActionItem);
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -274,40 +1028,178 @@ mod tests {
             compatibility: Some("COMPATIBILITY".to_string()),
             url: Some("URL".to_string()),
             civmods_internal_version_id: None,
+            extra: vec![],
+            elements: vec![],
+            order: [
+                "Name",
+                "Description",
+                "Authors",
+                "Package",
+                "SpecialThanks",
+                "Compatibility",
+                "URL",
+                "Version",
+                "AffectsSavedGames",
+            ]
+            .map(str::to_string)
+            .to_vec(),
         };
 
         assert_eq!(actual, expected);
 
+        let affects_saved_games = |xml: &str| -> anyhow::Result<bool> {
+            Ok(quick_xml::de::from_str::<Properties>(xml)?.affects_saved_games)
+        };
+        for (value, expected) in [("0", false), ("1", true), ("false", false), ("true", true)] {
+            let xml =
+                format!("<Properties><AffectsSavedGames>{value}</AffectsSavedGames></Properties>");
+            assert_eq!(affects_saved_games(&xml)?, expected, "{value}");
+        }
+        assert!(affects_saved_games("<Properties></Properties>")?);
+        assert!(
+            affects_saved_games(
+                "<Properties><AffectsSavedGames>maybe</AffectsSavedGames></Properties>"
+            )
+            .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mod_properties_round_trip() -> anyhow::Result<()> {
+        let actual = quick_xml::de::from_str::<Properties>(indoc! {r#"
+            <Properties>
+                <ShowInBrowser>1</ShowInBrowser>
+                <Name>NAME</Name>
+                <Links><Link url="https://example.com">Home</Link></Links>
+                <ShowInBrowser>0</ShowInBrowser>
+                <AffectsSavedGames>0</AffectsSavedGames>
+                <Links><Link url="https://example.org">Mirror</Link></Links>
+            </Properties>
+        "#})?;
+
         assert_eq!(
-            quick_xml::de::from_str::<Properties>(indoc! {r#"
-                <Properties>
-                    <AffectsSavedGames>0</AffectsSavedGames>
-                </Properties>
-            "#})?,
-            Properties {
-                affects_saved_games: false,
-                ..Default::default()
-            },
+            actual.elements[0].1.children[0].1.attributes["url"],
+            "https://example.com"
+        );
+        assert_eq!(
+            actual.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            [
+                "ShowInBrowser",
+                "Name",
+                "ShowInBrowser",
+                "AffectsSavedGames"
+            ],
         );
 
+        let serialized = quick_xml::se::to_string_with_root("Properties", &actual)?;
         assert_eq!(
-            quick_xml::de::from_str::<Properties>(indoc! {r#"
-                <Properties>
-                    <AffectsSavedGames>1</AffectsSavedGames>
-                </Properties>
-            "#})?,
-            Properties {
-                affects_saved_games: true,
-                ..Default::default()
-            },
+            serialized,
+            concat!(
+                "<Properties><ShowInBrowser>1</ShowInBrowser><Name>NAME</Name>",
+                r#"<Links><Link url="https://example.com">Home</Link></Links>"#,
+                "<ShowInBrowser>0</ShowInBrowser><AffectsSavedGames>0</AffectsSavedGames>",
+                r#"<Links><Link url="https://example.org">Mirror</Link></Links></Properties>"#,
+            )
         );
+        assert_eq!(quick_xml::de::from_str::<Properties>(&serialized)?, actual);
 
+        let mut properties = actual;
+        properties.set("ShowInBrowser", "2")?;
         assert_eq!(
-            quick_xml::de::from_str::<Properties>("<Properties></Properties>")?,
-            Properties {
-                affects_saved_games: true,
-                ..Default::default()
-            },
+            properties.extra,
+            [
+                ("ShowInBrowser".to_string(), "2".to_string()),
+                ("ShowInBrowser".to_string(), "0".to_string()),
+            ]
+        );
+        assert_eq!(
+            properties.set("AffectsSavedGames", "maybe"),
+            Err(BuildError::InvalidProperty {
+                name: "AffectsSavedGames".to_string(),
+                value: "maybe".to_string(),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mod_extra_properties() -> anyhow::Result<()> {
+        let actual = quick_xml::de::from_str::<Properties>(indoc! {r#"
+            <Properties>
+                <Name>NAME</Name>
+                <ShowInBrowser>1</ShowInBrowser>
+                <SortIndex>10</SortIndex>
+                <CivModsURL>https://civmods.com/mods/abc</CivModsURL>
+                <CivModsInternalVersionId>abc</CivModsInternalVersionId>
+                <CivModsVersion>1.0</CivModsVersion>
+            </Properties>
+        "#})?;
+
+        assert_eq!(actual.name.as_deref(), Some("NAME"));
+        assert_eq!(
+            actual
+                .extra
+                .iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            ["ShowInBrowser", "SortIndex", "CivModsURL", "CivModsVersion"],
+        );
+        assert_eq!(actual.get("SortIndex").as_deref(), Some("10"));
+        assert_eq!(
+            actual.civmods(),
+            Some(CivModsProperties {
+                internal_version_id: "abc".to_string(),
+                mod_url: "https://civmods.com/mods/abc".to_string(),
+                mod_version: Some("1.0".to_string()),
+                mod_category: None,
+                mod_version_date: None,
+            }),
+        );
+
+        let serialized = quick_xml::se::to_string_with_root("Properties", &actual)?;
+        assert_eq!(quick_xml::de::from_str::<Properties>(&serialized)?, actual);
+
+        assert_eq!(Properties::default().civmods(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_references() -> anyhow::Result<()> {
+        let actual = quick_xml::de::from_str::<Mod>(indoc! {r#"
+            <Mod id="ID">
+                <Dependencies>
+                    <Mod id="base-standard" title="LOC_MODULE_BASE_STANDARD_NAME"/>
+                </Dependencies>
+                <References>
+                    <Mod id="core" title="LOC_MODULE_CORE_NAME"/>
+                </References>
+            </Mod>
+        "#})?;
+
+        let dependency = |id: &str, title: &str| Dependency {
+            id: id.to_string(),
+            title: Some(title.to_string()),
+        };
+        assert_eq!(
+            actual.references,
+            [dependency("core", "LOC_MODULE_CORE_NAME")]
+        );
+        assert_eq!(
+            actual.all_dependencies().collect::<Vec<_>>(),
+            [
+                (
+                    DependencyKind::Required,
+                    &dependency("base-standard", "LOC_MODULE_BASE_STANDARD_NAME")
+                ),
+                (
+                    DependencyKind::Optional,
+                    &dependency("core", "LOC_MODULE_CORE_NAME")
+                ),
+            ],
         );
 
         Ok(())
@@ -325,12 +1217,18 @@ mod tests {
 
         let expected = ActionCriterion {
             id: "ID".to_string(),
-            always_met: true,
-            age_in_use: Some("AGE_ANTIQUITY".to_string()),
-            mod_in_use: Some(ActionCriterionModInUse {
-                mod_id: "MOD_ID".to_string(),
-                inverse: true,
-            }),
+            any: false,
+            conditions: vec![
+                CriterionCondition::AlwaysMet,
+                CriterionCondition::AgeInUse(ConditionValue {
+                    value: "AGE_ANTIQUITY".to_string(),
+                    inverse: false,
+                }),
+                CriterionCondition::ModInUse(ConditionValue {
+                    value: "MOD_ID".to_string(),
+                    inverse: true,
+                }),
+            ],
         };
 
         assert_eq!(actual, expected);
@@ -338,6 +1236,79 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_action_criteria_conditions() -> anyhow::Result<()> {
+        let actual = quick_xml::de::from_str::<ActionCriterion>(indoc! {r#"
+            <Criteria id="ID" any="true">
+                <AgeInUse>AGE_EXPLORATION</AgeInUse>
+                <AgeInUse>AGE_MODERN</AgeInUse>
+                <ConfigurationValueMatches>
+                    <Group>Game</Group>
+                    <ConfigurationId>Ruleset</ConfigurationId>
+                    <Value>RULESET_STANDARD</Value>
+                </ConfigurationValueMatches>
+                <PlayerIsHuman inverse="true">1</PlayerIsHuman>
+                <PlayerHasUnlock>
+                    <Unlock type="LEADER">LEADER_AMINA</Unlock>
+                </PlayerHasUnlock>
+            </Criteria>
+        "#})?;
+
+        let value = |value: &str| ConditionValue {
+            value: value.to_string(),
+            inverse: false,
+        };
+        let expected = ActionCriterion {
+            id: "ID".to_string(),
+            any: true,
+            conditions: vec![
+                CriterionCondition::AgeInUse(value("AGE_EXPLORATION")),
+                CriterionCondition::AgeInUse(value("AGE_MODERN")),
+                CriterionCondition::ConfigurationValueMatches(ConfigurationCondition {
+                    inverse: false,
+                    group: "Game".to_string(),
+                    configuration_id: "Ruleset".to_string(),
+                    value: "RULESET_STANDARD".to_string(),
+                }),
+                CriterionCondition::Unknown {
+                    name: "PlayerIsHuman".to_string(),
+                    element: RawElement {
+                        attributes: IndexMap::from([("inverse".to_string(), "true".to_string())]),
+                        text: Some("1".to_string()),
+                        children: vec![],
+                    },
+                },
+                CriterionCondition::Unknown {
+                    name: "PlayerHasUnlock".to_string(),
+                    element: RawElement {
+                        children: vec![(
+                            "Unlock".to_string(),
+                            RawElement {
+                                attributes: IndexMap::from([(
+                                    "type".to_string(),
+                                    "LEADER".to_string(),
+                                )]),
+                                text: Some("LEADER_AMINA".to_string()),
+                                children: vec![],
+                            },
+                        )],
+                        ..Default::default()
+                    },
+                },
+            ],
+        };
+
+        assert_eq!(actual, expected);
+
+        let serialized = quick_xml::se::to_string_with_root("Criteria", &actual)?;
+        assert_eq!(
+            quick_xml::de::from_str::<ActionCriterion>(&serialized)?,
+            expected
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_action_groups() -> anyhow::Result<()> {
         let actual = quick_xml::de::from_str::<ActionGroup>(indoc! {r#"
@@ -363,21 +1334,23 @@ mod tests {
                 load_order: Some(4),
             },
             actions: vec![ActionGroupActions {
-                update_database: vec![
-                    ActionItem {
-                        path: "A.sql".to_string(),
-                        locale: None,
-                    },
-                    ActionItem {
-                        path: "B.sql".to_string(),
-                        locale: None,
-                    },
-                    ActionItem {
-                        path: "C.sql".to_string(),
-                        locale: Some("de_DE".to_string()),
-                    },
-                ],
-                ..Default::default()
+                actions: vec![Action {
+                    children: vec![
+                        ActionChild::Item(ActionItem {
+                            path: "A.sql".to_string(),
+                            locale: None,
+                        }),
+                        ActionChild::Item(ActionItem {
+                            path: "B.sql".to_string(),
+                            locale: None,
+                        }),
+                        ActionChild::Item(ActionItem {
+                            path: "C.sql".to_string(),
+                            locale: Some("de_DE".to_string()),
+                        }),
+                    ],
+                    ..Action::new(ActionKind::UpdateDatabase)
+                }],
             }],
         };
 
@@ -385,4 +1358,83 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_action_catalog_round_trip() -> anyhow::Result<()> {
+        let actual = quick_xml::de::from_str::<ActionGroupActions>(indoc! {r#"
+            <Actions>
+                <UpdateIcons>
+                    <Item>icons/icons.xml</Item>
+                </UpdateIcons>
+                <ScenarioScripts>
+                    <Item>scenario/test.js</Item>
+                </ScenarioScripts>
+                <UpdateFuture priority="2">
+                    <Item>future.xml</Item>
+                    <Options><Strict>1</Strict></Options>
+                    <Item>later.xml</Item>
+                </UpdateFuture>
+                <UpdateIcons>
+                    <Item>icons/more-icons.xml</Item>
+                </UpdateIcons>
+            </Actions>
+        "#})?;
+
+        let item = |path: &str| {
+            ActionChild::Item(ActionItem {
+                path: path.to_string(),
+                locale: None,
+            })
+        };
+        let action = |kind: ActionKind, children: Vec<ActionChild>| Action {
+            children,
+            ..Action::new(kind)
+        };
+        let expected = ActionGroupActions {
+            actions: vec![
+                action(ActionKind::UpdateIcons, vec![item("icons/icons.xml")]),
+                action(ActionKind::ScenarioScripts, vec![item("scenario/test.js")]),
+                Action {
+                    name: "UpdateFuture".to_string(),
+                    attributes: IndexMap::from([("priority".to_string(), "2".to_string())]),
+                    text: None,
+                    children: vec![
+                        item("future.xml"),
+                        ActionChild::Element(
+                            "Options".to_string(),
+                            RawElement {
+                                children: vec![(
+                                    "Strict".to_string(),
+                                    RawElement {
+                                        text: Some("1".to_string()),
+                                        ..Default::default()
+                                    },
+                                )],
+                                ..Default::default()
+                            },
+                        ),
+                        item("later.xml"),
+                    ],
+                },
+                action(ActionKind::UpdateIcons, vec![item("icons/more-icons.xml")]),
+            ],
+        };
+
+        assert_eq!(actual, expected);
+        assert_eq!(
+            actual
+                .items(ActionKind::UpdateIcons)
+                .map(|item| item.path.as_str())
+                .collect::<Vec<_>>(),
+            ["icons/icons.xml", "icons/more-icons.xml"]
+        );
+
+        let serialized = quick_xml::se::to_string_with_root("Actions", &actual)?;
+        assert_eq!(
+            quick_xml::de::from_str::<ActionGroupActions>(&serialized)?,
+            expected
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,549 @@
+use crate::Mod;
+use crate::database::read_database_rows;
+use crate::files::read_mod_file;
+use crate::graph::DependencyGraph;
+use crate::load_order::{by_load_order, load_order};
+use crate::schema::ActionKind;
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// The game's reference locale, used when a string is missing in another one.
+pub const DEFAULT_LOCALE: &str = "en_US";
+
+/// Normalizes a locale as the game writes it, e.g. `en-us` to `en_US`.
+pub fn normalize_locale(locale: &str) -> String {
+    let locale = locale.trim().replace('-', "_");
+    match locale.split_once('_') {
+        Some((language, region)) => {
+            format!("{}_{}", language.to_lowercase(), region.to_uppercase())
+        }
+        None => locale.to_lowercase(),
+    }
+}
+
+/// A `LOC_` key defined by a text file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextRow {
+    pub locale: String,
+    pub tag: String,
+    pub text: String,
+}
+
+/// A text file referenced by a modinfo, with the locale of its `UpdateText`
+/// item if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextFile<'a> {
+    /// `None` for `<LocalizedText>` entries.
+    pub action_group: Option<&'a str>,
+    pub path: &'a str,
+    pub locale: Option<&'a str>,
+}
+
+/// Reads the strings of a text file:
+///
+/// ```xml
+/// <Database>
+///     <EnglishText>
+///         <Row Tag="LOC_MOD_NAME"><Text>My mod</Text></Row>
+///     </EnglishText>
+///     <LocalizedText>
+///         <Row Tag="LOC_MOD_NAME" Language="fr_FR"><Text>Mon mod</Text></Row>
+///     </LocalizedText>
+/// </Database>
+/// ```
+///
+/// Rows without a `Language` use `default_locale`, e.g. the locale of the
+/// `UpdateText` item, except in `<EnglishText>`.
+pub fn parse_text_xml(xml: &str, default_locale: &str) -> Result<Vec<TextRow>, quick_xml::Error> {
+    let rows = read_database_rows(xml)?;
+    Ok(rows
+        .iter()
+        .filter(|row| matches!(row.element.as_str(), "Row" | "Replace"))
+        .filter_map(|row| {
+            let locale = match row.get("Language") {
+                _ if row.table == "EnglishText" => DEFAULT_LOCALE.to_string(),
+                Some(language) => normalize_locale(language),
+                None => normalize_locale(default_locale),
+            };
+            Some(TextRow {
+                locale,
+                tag: row.get("Tag")?.to_string(),
+                text: row.get("Text").unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Reads a text file of a mod. Failures are logged and yield no rows.
+pub(crate) fn read_text_file(mod_folder: &Path, file: &TextFile) -> Vec<TextRow> {
+    let Some((file_path, xml)) = read_mod_file(mod_folder, file.path) else {
+        return vec![];
+    };
+    parse_text_xml(&xml, file.locale.unwrap_or(DEFAULT_LOCALE)).unwrap_or_else(|err| {
+        log::warn!("Invalid XML in {}: {err}", file_path.display());
+        vec![]
+    })
+}
+
+/// Strings by locale, then by `LOC_` key.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LocalizedStrings {
+    tables: IndexMap<String, IndexMap<String, String>>,
+}
+
+impl LocalizedStrings {
+    /// Adds a string. Like the game, a later definition replaces an earlier one.
+    pub fn insert(&mut self, locale: &str, tag: impl Into<String>, text: impl Into<String>) {
+        self.tables
+            .entry(normalize_locale(locale))
+            .or_default()
+            .insert(tag.into(), text.into());
+    }
+
+    pub fn extend(&mut self, rows: impl IntoIterator<Item = TextRow>) {
+        for row in rows {
+            self.insert(&row.locale, row.tag, row.text);
+        }
+    }
+
+    pub fn locales(&self) -> impl Iterator<Item = &str> {
+        self.tables.keys().map(String::as_str)
+    }
+
+    /// Every string of `locale`, in definition order.
+    pub fn table(&self, locale: &str) -> Option<&IndexMap<String, String>> {
+        self.tables.get(&normalize_locale(locale))
+    }
+
+    /// The string of `tag` in `locale`, without fallback.
+    pub fn get(&self, locale: &str, tag: &str) -> Option<&str> {
+        self.table(locale)?.get(tag).map(String::as_str)
+    }
+
+    /// Resolves `value` when it is a `LOC_` key, in `locale` or else in
+    /// [`DEFAULT_LOCALE`]. Other values, and unknown keys, are returned as is.
+    pub fn resolve<'s>(&'s self, locale: &str, value: &'s str) -> &'s str {
+        if !value.starts_with("LOC_") {
+            return value;
+        }
+        self.get(locale, value)
+            .or_else(|| self.get(DEFAULT_LOCALE, value))
+            .unwrap_or(value)
+    }
+}
+
+impl Mod {
+    /// Text files of `<LocalizedText>`, then of `UpdateText` actions.
+    pub fn text_files(&self) -> impl Iterator<Item = TextFile<'_>> {
+        let localized_text = self.localized_text.iter().map(|path| TextFile {
+            action_group: None,
+            path,
+            locale: None,
+        });
+        let update_text = self.action_groups.iter().flat_map(|group| {
+            group
+                .actions
+                .iter()
+                .flat_map(|actions| actions.items(ActionKind::UpdateText))
+                .map(|item| TextFile {
+                    action_group: Some(&group.id),
+                    path: &item.path,
+                    locale: item.locale.as_deref(),
+                })
+        });
+        localized_text.chain(update_text)
+    }
+
+    /// Loads the strings of every text file of the mod. `mod_folder` is the
+    /// folder containing the `.modinfo` file.
+    pub fn load_text(&self, mod_folder: impl AsRef<Path>) -> LocalizedStrings {
+        let mut strings = LocalizedStrings::default();
+        for file in self.text_files() {
+            strings.extend(read_text_file(mod_folder.as_ref(), &file));
+        }
+        strings
+    }
+
+    /// Coverage of every locale the mod declares on its `UpdateText` items or
+    /// defines strings for, except [`DEFAULT_LOCALE`].
+    pub fn translation_coverage(&self, mod_folder: impl AsRef<Path>) -> Vec<LocaleCoverage> {
+        let strings = self.load_text(mod_folder);
+        let mut locales: Vec<String> = self
+            .text_files()
+            .filter_map(|file| file.locale.map(normalize_locale))
+            .collect();
+        locales.extend(strings.locales().map(str::to_string));
+        let mut seen = HashSet::new();
+        locales
+            .into_iter()
+            .filter(|locale| locale != DEFAULT_LOCALE && seen.insert(locale.clone()))
+            .map(|locale| strings.coverage(&locale))
+            .collect()
+    }
+
+    /// The `<Name>` of the mod, resolved in `locale`.
+    pub fn localized_name<'s>(
+        &'s self,
+        strings: &'s LocalizedStrings,
+        locale: &str,
+    ) -> Option<&'s str> {
+        let name = self.properties.name.as_deref()?;
+        Some(strings.resolve(locale, name))
+    }
+
+    /// The `<Description>` of the mod, resolved in `locale`.
+    pub fn localized_description<'s>(
+        &'s self,
+        strings: &'s LocalizedStrings,
+        locale: &str,
+    ) -> Option<&'s str> {
+        let description = self.properties.description.as_deref()?;
+        Some(strings.resolve(locale, description))
+    }
+}
+
+/// How much of the [`DEFAULT_LOCALE`] strings of a mod a locale translates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LocaleCoverage {
+    pub locale: String,
+    /// Number of en_US keys translated in the locale.
+    pub present: usize,
+    /// Number of en_US keys.
+    pub total: usize,
+    /// en_US keys missing from the locale, in definition order.
+    pub missing: Vec<String>,
+}
+
+impl LocaleCoverage {
+    /// Translated share of the keys, from 0 to 1. A mod without any string
+    /// is fully translated.
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.present as f64 / self.total as f64
+        }
+    }
+}
+
+impl LocalizedStrings {
+    /// Coverage of `locale` compared to [`DEFAULT_LOCALE`].
+    pub fn coverage(&self, locale: &str) -> LocaleCoverage {
+        let reference = self.table(DEFAULT_LOCALE);
+        let translated = self.table(locale);
+        let missing: Vec<String> = reference
+            .into_iter()
+            .flat_map(|table| table.keys())
+            .filter(|tag| !translated.is_some_and(|table| table.contains_key(*tag)))
+            .cloned()
+            .collect();
+        let total = reference.map_or(0, |table| table.len());
+        LocaleCoverage {
+            locale: normalize_locale(locale),
+            present: total - missing.len(),
+            total,
+            missing,
+        }
+    }
+}
+
+/// A mod defining the key of a [`TextConflict`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TextDefinition {
+    pub mod_id: String,
+    /// `None` for `<LocalizedText>` entries.
+    pub action_group: Option<String>,
+    pub path: String,
+    pub load_order: i64,
+    pub text: String,
+}
+
+/// Several mods defining the same `LOC_` key in the same locale, with
+/// different texts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TextConflict {
+    pub locale: String,
+    pub tag: String,
+    /// In load order: the last one is the text the game shows.
+    pub definitions: Vec<TextDefinition>,
+    pub winner: String,
+}
+
+/// Parses the text files of `mods`, given with the folder of their modinfo,
+/// and reports the keys defined by more than one mod.
+///
+/// `<LocalizedText>` files are assumed to load before any action group, in
+/// dependency order. Keys defined with the same text everywhere are not
+/// reported, as players cannot tell the difference.
+pub fn detect_text_conflicts<'a>(
+    mods: impl IntoIterator<Item = (&'a Mod, &'a Path)>,
+) -> Vec<TextConflict> {
+    let mods: Vec<(&Mod, &Path)> = mods.into_iter().collect();
+
+    let mut files: Vec<(&Mod, &Path, TextFile, i64)> = vec![];
+    let graph = DependencyGraph::new(mods.iter().map(|(modinfo, _)| *modinfo));
+    for id in graph.topological_order() {
+        // Every copy of a mod installed twice, each in its own folder
+        for (modinfo, folder) in mods.iter().filter(|(modinfo, _)| modinfo.id == id) {
+            let localized_text = modinfo
+                .text_files()
+                .filter(|file| file.action_group.is_none());
+            files.extend(localized_text.map(|file| (*modinfo, *folder, file, 0)));
+        }
+    }
+    for (modinfo, folder, group) in by_load_order(mods.iter().copied()) {
+        let update_text = modinfo
+            .text_files()
+            .filter(|file| file.action_group == Some(group.id.as_str()));
+        files.extend(update_text.map(|file| (modinfo, folder, file, load_order(group))));
+    }
+
+    let mut keys: IndexMap<(String, String), Vec<TextDefinition>> = IndexMap::new();
+    for (modinfo, folder, file, load_order) in files {
+        for row in read_text_file(folder, &file) {
+            keys.entry((row.locale, row.tag))
+                .or_default()
+                .push(TextDefinition {
+                    mod_id: modinfo.id.clone(),
+                    action_group: file.action_group.map(str::to_string),
+                    path: file.path.to_string(),
+                    load_order,
+                    text: row.text,
+                });
+        }
+    }
+
+    keys.into_iter()
+        .filter_map(|((locale, tag), definitions)| {
+            let winner = definitions.last()?;
+            let other_mods = definitions.iter().any(|d| d.mod_id != winner.mod_id);
+            let other_texts = definitions.iter().any(|d| d.text != winner.text);
+            if !other_mods || !other_texts {
+                return None;
+            }
+            Some(TextConflict {
+                locale,
+                tag,
+                winner: winner.mod_id.clone(),
+                definitions,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ActionGroup;
+    use indoc::indoc;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_text_xml() -> anyhow::Result<()> {
+        let rows = parse_text_xml(
+            indoc! {r#"
+                <?xml version="1.0" encoding="utf-8"?>
+                <Database>
+                    <EnglishText>
+                        <Row Tag="LOC_A"><Text>A &amp; B</Text></Row>
+                    </EnglishText>
+                    <LocalizedText>
+                        <Replace Tag="LOC_A" Language="fr-fr"><Text>A et B</Text></Replace>
+                        <Row><Tag>LOC_B</Tag><Text><![CDATA[<b>B</b>]]></Text></Row>
+                        <Row Tag="LOC_EMPTY"/>
+                    </LocalizedText>
+                </Database>
+            "#},
+            "de_DE",
+        )?;
+        let row = |locale: &str, tag: &str, text: &str| TextRow {
+            locale: locale.to_string(),
+            tag: tag.to_string(),
+            text: text.to_string(),
+        };
+        assert_eq!(
+            rows,
+            [
+                row("en_US", "LOC_A", "A & B"),
+                row("fr_FR", "LOC_A", "A et B"),
+                row("de_DE", "LOC_B", "<b>B</b>"),
+                row("de_DE", "LOC_EMPTY", ""),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_localized_name() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        fs::create_dir(dir.path().join("text"))?;
+        fs::write(
+            dir.path().join("text/en_us.xml"),
+            r#"<Database><EnglishText><Row Tag="LOC_MOD_NAME"><Text>Map pack</Text></Row><Row Tag="LOC_MOD_DESCRIPTION"><Text>Maps</Text></Row></EnglishText></Database>"#,
+        )?;
+        fs::write(
+            dir.path().join("text/fr_fr.xml"),
+            r#"<Database><LocalizedText><Row Tag="LOC_MOD_NAME"><Text>Cartes</Text></Row></LocalizedText></Database>"#,
+        )?;
+        let modinfo = Mod::builder("maps")
+            .name("LOC_MOD_NAME")
+            .description("LOC_MOD_DESCRIPTION")
+            .localized_text("text/en_us.xml")
+            .action_group(ActionGroup::builder("shell", "shell").localized_action(
+                ActionKind::UpdateText,
+                "text/fr_fr.xml",
+                "fr_FR",
+            ))
+            .build()?;
+
+        let strings = modinfo.load_text(dir.path());
+        assert_eq!(strings.locales().collect::<Vec<_>>(), ["en_US", "fr_FR"]);
+        assert_eq!(modinfo.localized_name(&strings, "fr_FR"), Some("Cartes"));
+        assert_eq!(modinfo.localized_name(&strings, "de_DE"), Some("Map pack"));
+        assert_eq!(
+            modinfo.localized_description(&strings, "fr_FR"),
+            Some("Maps")
+        );
+        assert_eq!(strings.resolve("en_US", "LOC_UNKNOWN"), "LOC_UNKNOWN");
+        assert_eq!(strings.resolve("en_US", "Plain name"), "Plain name");
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_text_conflicts() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let text = |tags: &[(&str, &str)]| {
+            let rows: Vec<String> = tags
+                .iter()
+                .map(|(tag, text)| format!(r#"<Row Tag="{tag}"><Text>{text}</Text></Row>"#))
+                .collect();
+            format!(
+                "<Database><EnglishText>{}</EnglishText></Database>",
+                rows.concat()
+            )
+        };
+        let a_folder = dir.path().join("a");
+        let b_folder = dir.path().join("b");
+        fs::create_dir(&a_folder)?;
+        fs::create_dir(&b_folder)?;
+        fs::write(
+            a_folder.join("base.xml"),
+            text(&[("LOC_UNIT", "Warrior"), ("LOC_SAME", "Same")]),
+        )?;
+        fs::write(a_folder.join("late.xml"), text(&[("LOC_LATE", "A")]))?;
+        fs::write(
+            b_folder.join("text.xml"),
+            text(&[
+                ("LOC_UNIT", "Fighter"),
+                ("LOC_SAME", "Same"),
+                ("LOC_LATE", "B"),
+            ]),
+        )?;
+
+        let a = Mod::builder("a")
+            .localized_text("base.xml")
+            .action_group(
+                ActionGroup::builder("game", "game")
+                    .load_order(10)
+                    .action(ActionKind::UpdateText, "late.xml"),
+            )
+            .build()?;
+        let b = Mod::builder("b")
+            .action_group(
+                ActionGroup::builder("game", "game").action(ActionKind::UpdateText, "text.xml"),
+            )
+            .build()?;
+
+        let conflicts = detect_text_conflicts([(&a, a_folder.as_path()), (&b, b_folder.as_path())]);
+        let summary: Vec<(&str, &str)> = conflicts
+            .iter()
+            .map(|conflict| (conflict.tag.as_str(), conflict.winner.as_str()))
+            .collect();
+        assert_eq!(summary, [("LOC_UNIT", "b"), ("LOC_LATE", "a")]);
+        assert_eq!(conflicts[0].locale, "en_US");
+        assert_eq!(conflicts[0].definitions[0].text, "Warrior");
+        Ok(())
+    }
+
+    #[test]
+    fn test_translation_coverage() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        fs::write(
+            dir.path().join("en.xml"),
+            r#"<Database><EnglishText><Row Tag="LOC_A"><Text>A</Text></Row><Row Tag="LOC_B"><Text>B</Text></Row></EnglishText></Database>"#,
+        )?;
+        fs::write(
+            dir.path().join("fr.xml"),
+            r#"<Database><LocalizedText><Row Tag="LOC_B"><Text>B</Text></Row><Row Tag="LOC_EXTRA"><Text>?</Text></Row></LocalizedText></Database>"#,
+        )?;
+        let modinfo = Mod::builder("a_mod")
+            .localized_text("en.xml")
+            .action_group(
+                ActionGroup::builder("shell", "shell")
+                    .localized_action(ActionKind::UpdateText, "fr.xml", "fr_FR")
+                    .localized_action(ActionKind::UpdateText, "missing.xml", "de-de"),
+            )
+            .build()?;
+
+        let coverage = modinfo.translation_coverage(dir.path());
+        assert_eq!(
+            coverage,
+            [
+                LocaleCoverage {
+                    locale: "fr_FR".to_string(),
+                    present: 1,
+                    total: 2,
+                    missing: vec!["LOC_A".to_string()],
+                },
+                LocaleCoverage {
+                    locale: "de_DE".to_string(),
+                    present: 0,
+                    total: 2,
+                    missing: vec!["LOC_A".to_string(), "LOC_B".to_string()],
+                },
+            ]
+        );
+        assert_eq!(coverage[0].ratio(), 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_text_conflicts_duplicate_ids() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let folders = ["old", "new", "other"].map(|name| dir.path().join(name));
+        for folder in &folders {
+            let name = folder.file_name().unwrap().to_string_lossy();
+            fs::create_dir(folder)?;
+            fs::write(
+                folder.join(format!("{name}.xml")),
+                format!(
+                    r#"<Database><EnglishText><Row Tag="LOC_X"><Text>{name}</Text></Row></EnglishText></Database>"#
+                ),
+            )?;
+        }
+        let old = Mod::builder("dup").localized_text("old.xml").build()?;
+        let new = Mod::builder("dup")
+            .action_group(
+                ActionGroup::builder("game", "game").action(ActionKind::UpdateText, "new.xml"),
+            )
+            .build()?;
+        let other = Mod::builder("other").localized_text("other.xml").build()?;
+
+        // Each copy reads its files from its own folder
+        let conflicts = detect_text_conflicts([
+            (&old, folders[0].as_path()),
+            (&new, folders[1].as_path()),
+            (&other, folders[2].as_path()),
+        ]);
+        let texts: Vec<&str> = conflicts[0]
+            .definitions
+            .iter()
+            .map(|definition| definition.text.as_str())
+            .collect();
+        assert_eq!(texts, ["old", "other", "new"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,339 @@
+use crate::Mod;
+use crate::schema::DependencyKind;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Scopes accepted by the game for `<ActionGroup scope="...">`.
+pub const KNOWN_SCOPES: &[&str] = &["game", "shell"];
+
+/// The game stores `LoadOrder` as a 32-bit integer.
+pub const LOAD_ORDER_RANGE: std::ops::RangeInclusive<i64> = (i32::MIN as i64)..=(i32::MAX as i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The mod loads, but probably not the way the author intended.
+    Warning,
+    /// The game ignores the mod, or part of it.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    EmptyModId,
+    SelfDependency,
+    DuplicateCriteriaId {
+        criteria: String,
+    },
+    DuplicateActionGroupId {
+        action_group: String,
+    },
+    UndefinedCriteria {
+        action_group: String,
+        criteria: String,
+    },
+    UnknownScope {
+        action_group: String,
+        scope: String,
+    },
+    LoadOrderOutOfRange {
+        action_group: String,
+        load_order: i64,
+    },
+    /// `action` is the element name, e.g. `UpdateDatabase`, or `LocalizedText`
+    /// for entries outside of any action group.
+    EmptyItemPath {
+        action_group: Option<String>,
+        action: String,
+    },
+}
+
+/// A single problem found by [`Mod::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+}
+
+impl From<DiagnosticKind> for Diagnostic {
+    fn from(kind: DiagnosticKind) -> Self {
+        let severity = match kind {
+            DiagnosticKind::UnknownScope { .. }
+            | DiagnosticKind::LoadOrderOutOfRange { .. }
+            | DiagnosticKind::EmptyItemPath { .. } => Severity::Warning,
+            _ => Severity::Error,
+        };
+        Self { severity, kind }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            DiagnosticKind::EmptyModId => write!(f, "Mod id is empty"),
+            DiagnosticKind::SelfDependency => write!(f, "Mod depends on itself"),
+            DiagnosticKind::DuplicateCriteriaId { criteria } => {
+                write!(f, "Criteria '{criteria}' is defined more than once")
+            }
+            DiagnosticKind::DuplicateActionGroupId { action_group } => {
+                write!(f, "ActionGroup '{action_group}' is defined more than once")
+            }
+            DiagnosticKind::UndefinedCriteria {
+                action_group,
+                criteria,
+            } => write!(
+                f,
+                "ActionGroup '{action_group}' uses undefined criteria '{criteria}'"
+            ),
+            DiagnosticKind::UnknownScope {
+                action_group,
+                scope,
+            } => write!(
+                f,
+                "ActionGroup '{action_group}' has unknown scope '{scope}'"
+            ),
+            DiagnosticKind::LoadOrderOutOfRange {
+                action_group,
+                load_order,
+            } => write!(
+                f,
+                "ActionGroup '{action_group}' has out of range LoadOrder {load_order}"
+            ),
+            DiagnosticKind::EmptyItemPath {
+                action_group: Some(action_group),
+                action,
+            } => write!(f, "ActionGroup '{action_group}' has an empty {action} item"),
+            DiagnosticKind::EmptyItemPath {
+                action_group: None,
+                action,
+            } => write!(f, "{action} has an empty file entry"),
+        }
+    }
+}
+
+impl Mod {
+    /// Checks the parsed modinfo for mistakes that make the game silently
+    /// ignore the mod, or part of it.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = vec![];
+
+        if self.id.trim().is_empty() {
+            diagnostics.push(DiagnosticKind::EmptyModId.into());
+        }
+        // Only a required dependency on itself matters, a self-reference is harmless.
+        if self
+            .dependencies_of(DependencyKind::Required)
+            .iter()
+            .any(|dependency| dependency.id == self.id)
+        {
+            diagnostics.push(DiagnosticKind::SelfDependency.into());
+        }
+
+        let mut criteria_ids = HashSet::new();
+        for criterion in &self.action_criteria {
+            if !criteria_ids.insert(criterion.id.as_str()) {
+                diagnostics.push(
+                    DiagnosticKind::DuplicateCriteriaId {
+                        criteria: criterion.id.clone(),
+                    }
+                    .into(),
+                );
+            }
+        }
+
+        let mut action_group_ids = HashSet::new();
+        for group in &self.action_groups {
+            if !action_group_ids.insert(group.id.as_str()) {
+                diagnostics.push(
+                    DiagnosticKind::DuplicateActionGroupId {
+                        action_group: group.id.clone(),
+                    }
+                    .into(),
+                );
+            }
+            if let Some(criteria) = &group.criteria
+                && !criteria_ids.contains(criteria.as_str())
+            {
+                diagnostics.push(
+                    DiagnosticKind::UndefinedCriteria {
+                        action_group: group.id.clone(),
+                        criteria: criteria.clone(),
+                    }
+                    .into(),
+                );
+            }
+            if let Some(scope) = &group.scope
+                && !KNOWN_SCOPES.contains(&scope.as_str())
+            {
+                diagnostics.push(
+                    DiagnosticKind::UnknownScope {
+                        action_group: group.id.clone(),
+                        scope: scope.clone(),
+                    }
+                    .into(),
+                );
+            }
+            if let Some(load_order) = group.properties.load_order
+                && !LOAD_ORDER_RANGE.contains(&load_order)
+            {
+                diagnostics.push(
+                    DiagnosticKind::LoadOrderOutOfRange {
+                        action_group: group.id.clone(),
+                        load_order,
+                    }
+                    .into(),
+                );
+            }
+            for action in group.actions.iter().flat_map(|actions| actions.iter()) {
+                if action.items().any(|item| item.path.trim().is_empty()) {
+                    diagnostics.push(
+                        DiagnosticKind::EmptyItemPath {
+                            action_group: Some(group.id.clone()),
+                            action: action.name.clone(),
+                        }
+                        .into(),
+                    );
+                }
+            }
+        }
+
+        if self
+            .localized_text
+            .iter()
+            .any(|file| file.trim().is_empty())
+        {
+            diagnostics.push(
+                DiagnosticKind::EmptyItemPath {
+                    action_group: None,
+                    action: "LocalizedText".to_string(),
+                }
+                .into(),
+            );
+        }
+
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_validate_valid_mod() {
+        let parsed = Mod::parse(
+            indoc! {r#"
+                <Mod id="a_mod">
+                    <Dependencies>
+                        <Mod id="base-standard"/>
+                    </Dependencies>
+                    <References>
+                        <Mod id="a_mod"/>
+                    </References>
+                    <ActionCriteria>
+                        <Criteria id="always"><AlwaysMet/></Criteria>
+                    </ActionCriteria>
+                    <ActionGroups>
+                        <ActionGroup id="game" scope="game" criteria="always">
+                            <Properties><LoadOrder>-1000</LoadOrder></Properties>
+                            <Actions>
+                                <UpdateDatabase><Item>data/a.sql</Item></UpdateDatabase>
+                            </Actions>
+                        </ActionGroup>
+                    </ActionGroups>
+                </Mod>
+            "#}
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(parsed.validate(), vec![]);
+    }
+
+    #[test]
+    fn test_validate_diagnostics() {
+        let parsed = Mod::parse(
+            indoc! {r#"
+                <Mod id="a_mod">
+                    <Dependencies>
+                        <Mod id="a_mod"/>
+                    </Dependencies>
+                    <ActionCriteria>
+                        <Criteria id="always"><AlwaysMet/></Criteria>
+                        <Criteria id="always"><AlwaysMet/></Criteria>
+                    </ActionCriteria>
+                    <ActionGroups>
+                        <ActionGroup id="game" scope="games" criteria="sometimes">
+                            <Properties><LoadOrder>99999999999</LoadOrder></Properties>
+                            <Actions>
+                                <UIScripts><Item> </Item></UIScripts>
+                            </Actions>
+                        </ActionGroup>
+                        <ActionGroup id="game" scope="game" criteria="always"/>
+                    </ActionGroups>
+                    <LocalizedText>
+                        <File></File>
+                    </LocalizedText>
+                </Mod>
+            "#}
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let game = || "game".to_string();
+        let expected: Vec<Diagnostic> = vec![
+            DiagnosticKind::SelfDependency.into(),
+            DiagnosticKind::DuplicateCriteriaId {
+                criteria: "always".to_string(),
+            }
+            .into(),
+            DiagnosticKind::UndefinedCriteria {
+                action_group: game(),
+                criteria: "sometimes".to_string(),
+            }
+            .into(),
+            DiagnosticKind::UnknownScope {
+                action_group: game(),
+                scope: "games".to_string(),
+            }
+            .into(),
+            DiagnosticKind::LoadOrderOutOfRange {
+                action_group: game(),
+                load_order: 99999999999,
+            }
+            .into(),
+            DiagnosticKind::EmptyItemPath {
+                action_group: Some(game()),
+                action: "UIScripts".to_string(),
+            }
+            .into(),
+            DiagnosticKind::DuplicateActionGroupId {
+                action_group: game(),
+            }
+            .into(),
+            DiagnosticKind::EmptyItemPath {
+                action_group: None,
+                action: "LocalizedText".to_string(),
+            }
+            .into(),
+        ];
+
+        assert_eq!(parsed.validate(), expected);
+        assert_eq!(
+            expected[2].to_string(),
+            "ActionGroup 'game' uses undefined criteria 'sometimes'"
+        );
+    }
+
+    #[test]
+    fn test_validate_empty_id() {
+        let parsed = Mod::parse(r#"<Mod id=" "></Mod>"#.as_bytes()).unwrap();
+        assert_eq!(
+            parsed.validate(),
+            vec![Diagnostic {
+                severity: Severity::Error,
+                kind: DiagnosticKind::EmptyModId,
+            }]
+        );
+    }
+}
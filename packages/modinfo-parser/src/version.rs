@@ -0,0 +1,306 @@
+use crate::Mod;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+/// What [`ModVersion`] understood of a version string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionScheme {
+    /// `1`, `1.6.1`, `v2.0-beta2`, `1.0b`, `1.2.3-hotfix`: numbers with an
+    /// optional label.
+    Numeric {
+        components: Vec<u64>,
+        /// `alpha`, `beta`, `rc`, `pre`, `a` or `b`, with an optional number,
+        /// which sorts before the release.
+        pre_release: Option<String>,
+        /// Any other label, e.g. `hotfix` or `final`, which sorts after the
+        /// release.
+        label: Option<String>,
+    },
+    /// `2025-02-11`, `2025/02/11` or `2025.02.11`.
+    Date { year: u32, month: u32, day: u32 },
+    /// Anything else, compared as text.
+    Unparsed,
+}
+
+/// A mod version as authors actually write them, with a consistent order:
+/// unparsed versions first, by text, then numeric versions, then dates.
+///
+/// Equality follows the order, so `1.0` equals `1`.
+#[derive(Debug, Clone)]
+pub struct ModVersion {
+    original: String,
+    scheme: VersionScheme,
+}
+
+fn parse_date(version: &str) -> Option<VersionScheme> {
+    let separator = version.chars().find(|c| !c.is_ascii_digit())?;
+    if !matches!(separator, '-' | '/' | '.') {
+        return None;
+    }
+    let parts: Vec<&str> = version.split(separator).collect();
+    let [year, month, day] = parts[..] else {
+        return None;
+    };
+    if year.len() != 4 || month.len() > 2 || day.len() > 2 {
+        return None;
+    }
+    let (year, month, day) = (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(VersionScheme::Date { year, month, day })
+}
+
+fn parse_numeric(version: &str) -> Option<VersionScheme> {
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version).trim();
+    let end = version
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(version.len());
+    let (numbers, rest) = version.split_at(end);
+    let components = numbers
+        .trim_end_matches('.')
+        .split('.')
+        .map(|component| component.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    let label = rest.trim_start_matches(['-', '+', '_', ' ']).to_lowercase();
+    let (pre_release, label) = match label.as_str() {
+        "" => (None, None),
+        _ if pre_release_rank(&label).is_some() => (Some(label), None),
+        _ => (None, Some(label)),
+    };
+    Some(VersionScheme::Numeric {
+        components,
+        pre_release,
+        label,
+    })
+}
+
+/// Orders known pre-release labels, e.g. `beta2` as `(1, 2)`, so that
+/// `beta10` comes after `beta2`. `None` for any other label.
+fn pre_release_rank(label: &str) -> Option<(u8, u64)> {
+    let end = label
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(label.len());
+    let (name, number) = label.split_at(end);
+    let rank = match name {
+        "a" | "alpha" => 0,
+        "b" | "beta" => 1,
+        "pre" => 2,
+        "rc" => 3,
+        _ => return None,
+    };
+    let number = number.trim_start_matches(['.', '-', '_', ' ']);
+    let number = match number {
+        "" => 0,
+        number => number.parse().ok()?,
+    };
+    Some((rank, number))
+}
+
+impl ModVersion {
+    pub fn parse(version: &str) -> Self {
+        let trimmed = version.trim();
+        let scheme = parse_date(trimmed)
+            .or_else(|| parse_numeric(trimmed))
+            .unwrap_or(VersionScheme::Unparsed);
+        Self {
+            original: version.to_string(),
+            scheme,
+        }
+    }
+
+    pub fn scheme(&self) -> &VersionScheme {
+        &self.scheme
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.original
+    }
+
+    fn rank(&self) -> u8 {
+        match self.scheme {
+            VersionScheme::Unparsed => 0,
+            VersionScheme::Numeric { .. } => 1,
+            VersionScheme::Date { .. } => 2,
+        }
+    }
+}
+
+/// Compares numbers as if the shorter list were padded with zeros.
+fn compare_components(a: &[u64], b: &[u64]) -> Ordering {
+    let length = a.len().max(b.len());
+    let padded = |components: &[u64], index| components.get(index).copied().unwrap_or_default();
+    (0..length)
+        .map(|index| padded(a, index).cmp(&padded(b, index)))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+impl Ord for ModVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        use VersionScheme::*;
+        match (&self.scheme, &other.scheme) {
+            (
+                Numeric {
+                    components: a,
+                    pre_release: a_pre,
+                    label: a_label,
+                },
+                Numeric {
+                    components: b,
+                    pre_release: b_pre,
+                    label: b_label,
+                },
+            ) => compare_components(a, b)
+                .then_with(|| match (a_pre, b_pre) {
+                    (None, None) => Ordering::Equal,
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some(_), None) => Ordering::Less,
+                    (Some(a), Some(b)) => pre_release_rank(a).cmp(&pre_release_rank(b)),
+                })
+                .then_with(|| a_label.cmp(b_label)),
+            (
+                Date { year, month, day },
+                Date {
+                    year: other_year,
+                    month: other_month,
+                    day: other_day,
+                },
+            ) => (year, month, day).cmp(&(other_year, other_month, other_day)),
+            (Unparsed, Unparsed) => self.original.trim().cmp(other.original.trim()),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for ModVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ModVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for ModVersion {}
+
+impl FromStr for ModVersion {
+    type Err = Infallible;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(version))
+    }
+}
+
+impl fmt::Display for ModVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.original)
+    }
+}
+
+impl Serialize for ModVersion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.original)
+    }
+}
+
+impl Mod {
+    /// The `version` attribute of `<Mod>`, or else the `<Version>` property.
+    pub fn mod_version(&self) -> Option<ModVersion> {
+        self.version
+            .as_deref()
+            .or(self.properties.version.as_deref())
+            .filter(|version| !version.trim().is_empty())
+            .map(ModVersion::parse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let numeric = |components: &[u64], pre_release: Option<&str>| VersionScheme::Numeric {
+            components: components.to_vec(),
+            pre_release: pre_release.map(str::to_string),
+            label: None,
+        };
+        let labeled = |components: &[u64], label: &str| VersionScheme::Numeric {
+            components: components.to_vec(),
+            pre_release: None,
+            label: Some(label.to_string()),
+        };
+        let cases = [
+            ("1", numeric(&[1], None)),
+            ("1.6.1", numeric(&[1, 6, 1], None)),
+            ("v2.0-beta", numeric(&[2, 0], Some("beta"))),
+            ("1.0b", numeric(&[1, 0], Some("b"))),
+            ("1.0-RC.2", numeric(&[1, 0], Some("rc.2"))),
+            (" 3. ", numeric(&[3], None)),
+            ("1.2.3-hotfix", labeled(&[1, 2, 3], "hotfix")),
+            ("1.0 final", labeled(&[1, 0], "final")),
+            ("1.0-beautiful", labeled(&[1, 0], "beautiful")),
+            (
+                "2025-02-11",
+                VersionScheme::Date {
+                    year: 2025,
+                    month: 2,
+                    day: 11,
+                },
+            ),
+            (
+                "2025.2.11",
+                VersionScheme::Date {
+                    year: 2025,
+                    month: 2,
+                    day: 11,
+                },
+            ),
+            ("beta", VersionScheme::Unparsed),
+            ("", VersionScheme::Unparsed),
+        ];
+        for (version, expected) in cases {
+            assert_eq!(ModVersion::parse(version).scheme(), &expected, "{version}");
+        }
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        let sorted = [
+            "alpha",
+            "beta",
+            "0.9",
+            "1-beta",
+            "1",
+            "1.0.1",
+            "1.2",
+            "1.2-hotfix",
+            "v1.10",
+            "2.0-alpha",
+            "2.0-beta2",
+            "2.0-beta10",
+            "2.0-rc1",
+            "2",
+            "2 final",
+            "2024-12-31",
+            "2025-01-01",
+        ];
+        let mut versions: Vec<ModVersion> =
+            sorted.iter().rev().map(|v| v.parse().unwrap()).collect();
+        versions.sort();
+        let strings: Vec<&str> = versions.iter().map(ModVersion::as_str).collect();
+        assert_eq!(strings, sorted);
+
+        assert_eq!(ModVersion::parse("1.0"), ModVersion::parse("1"));
+        assert_eq!(ModVersion::parse("V2.0-Beta"), ModVersion::parse("2-beta"));
+        assert_eq!(ModVersion::parse("2.0b"), ModVersion::parse("2-beta"));
+        assert!(ModVersion::parse("1.10") > ModVersion::parse("1.9"));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::Mod;
+use crate::encoding::{self, DetectedEncoding};
+use encoding_rs::{UTF_8, UTF_16BE, UTF_16LE};
+use quick_xml::SeError;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Indentation of written modinfo files. The game's own files use tabs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    #[default]
+    Tabs,
+    Spaces(usize),
+}
+
+/// How [`Mod::write`] formats and encodes a modinfo file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    pub indent: Indent,
+    /// Use the encoding detected when parsing to keep the file as the author
+    /// had it.
+    pub encoding: DetectedEncoding,
+}
+
+impl WriteOptions {
+    /// Value of the `encoding` attribute of the XML declaration.
+    fn declared_encoding(&self) -> &'static str {
+        let encoding = self.encoding.encoding;
+        if encoding == UTF_8 {
+            "utf-8"
+        } else if encoding == UTF_16LE || encoding == UTF_16BE {
+            "utf-16"
+        } else {
+            encoding.name()
+        }
+    }
+}
+
+impl Mod {
+    /// Serializes the mod the way the game's own files look: with an XML
+    /// declaration and one element per line.
+    pub fn to_string_pretty(&self, options: &WriteOptions) -> Result<String, SeError> {
+        let mut output = format!(
+            "<?xml version=\"1.0\" encoding=\"{}\"?>\n",
+            options.declared_encoding()
+        );
+        let mut serializer = quick_xml::se::Serializer::new(&mut output);
+        match options.indent {
+            Indent::Tabs => serializer.indent('\t', 1),
+            Indent::Spaces(size) => serializer.indent(' ', size),
+        };
+        self.serialize(serializer)?;
+        output.push('\n');
+        Ok(output)
+    }
+
+    /// Writes the output of [`Mod::to_string_pretty`] in the encoding of
+    /// `options`. Characters the encoding cannot represent are written as
+    /// character references.
+    pub fn write(&self, mut writer: impl Write, options: &WriteOptions) -> io::Result<()> {
+        let text = self.to_string_pretty(options).map_err(io::Error::other)?;
+        writer.write_all(&encoding::encode(&text, options.encoding))
+    }
+
+    /// Writes the mod to `path`, see [`Mod::write`].
+    pub fn save(&self, path: impl AsRef<Path>, options: &WriteOptions) -> io::Result<()> {
+        self.write(File::create(path)?, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::WINDOWS_1252;
+    use indoc::indoc;
+
+    const MODINFO: &str = indoc! {r#"
+        <?xml version="1.0" encoding="utf-8"?>
+        <Mod id="a_mod" version="1" xmlns="ModInfo">
+        	<Properties>
+        		<Name>Ça marche</Name>
+        		<AffectsSavedGames>0</AffectsSavedGames>
+        	</Properties>
+        	<Dependencies>
+        		<Mod id="base-standard" title="LOC_MODULE_BASE_STANDARD_NAME"/>
+        	</Dependencies>
+        	<LocalizedText>
+        		<File>text/en_us/ModuleText.xml</File>
+        	</LocalizedText>
+        	<ActionCriteria>
+        		<Criteria id="always">
+        			<AlwaysMet/>
+        		</Criteria>
+        	</ActionCriteria>
+        	<ActionGroups>
+        		<ActionGroup id="game" scope="game" criteria="always">
+        			<Properties>
+        				<LoadOrder>10</LoadOrder>
+        			</Properties>
+        			<Actions>
+        				<UpdateText>
+        					<Item locale="fr_FR">text/fr_FR/ModuleText.xml</Item>
+        				</UpdateText>
+        			</Actions>
+        		</ActionGroup>
+        	</ActionGroups>
+        </Mod>
+    "#};
+
+    #[test]
+    fn test_to_string_pretty() -> anyhow::Result<()> {
+        let parsed = Mod::parse(MODINFO.as_bytes())?;
+        assert_eq!(parsed.to_string_pretty(&WriteOptions::default())?, MODINFO);
+
+        let options = WriteOptions {
+            indent: Indent::Spaces(2),
+            ..Default::default()
+        };
+        let output = parsed.to_string_pretty(&options)?;
+        assert!(output.contains("\n  <Properties>\n    <Name>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_keeps_encoding() -> anyhow::Result<()> {
+        for encoding in [UTF_8, UTF_16LE, WINDOWS_1252] {
+            let options = WriteOptions {
+                encoding: DetectedEncoding {
+                    encoding,
+                    bom: encoding != WINDOWS_1252,
+                },
+                ..Default::default()
+            };
+            let parsed = Mod::parse(MODINFO.as_bytes())?;
+            let mut output = vec![];
+            parsed.write(&mut output, &options)?;
+
+            let reparsed = Mod::parse_detailed(output.as_slice())?;
+            assert_eq!(reparsed.encoding, options.encoding);
+            assert_eq!(reparsed.modinfo, parsed);
+        }
+        Ok(())
+    }
+}
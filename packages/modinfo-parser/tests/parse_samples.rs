@@ -1,4 +1,4 @@
-use modinfo_parser::Mod;
+use modinfo_parser::{Mod, WriteOptions};
 
 #[test]
 fn parse_samples() {
@@ -10,6 +10,8 @@ fn parse_samples() {
             let serialized = parsed.to_string().unwrap();
             let reparsed = Mod::parse(serialized.as_bytes()).unwrap();
             assert_eq!(parsed, reparsed);
+            let pretty = parsed.to_string_pretty(&WriteOptions::default()).unwrap();
+            assert_eq!(parsed, Mod::parse(pretty.as_bytes()).unwrap());
         });
     });
 }